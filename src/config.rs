@@ -0,0 +1,439 @@
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use log::error;
+use log::info;
+use log::LevelFilter;
+use serde::Deserialize;
+
+use crate::journal;
+use crate::materialize::LinkMode;
+use crate::naming;
+use crate::naming::Naming;
+use crate::series::FilterWords;
+use crate::series::FilterWordsChanges;
+use crate::series::SplitCour;
+
+/// Config file looked up in the working directory when none is given
+pub const CONFIG_FILE_NAME: &str = "anime-organizer.yaml";
+
+/// Folder of the organizer in XDG config folders, like ~/.config/anime-organizer
+pub const CONFIG_DIR_NAME: &str = "anime-organizer";
+
+/// Changes to the filter words looked up in config folders
+pub const FILTER_WORDS_FILE_NAME: &str = "filter_words.yaml";
+
+/// Existing files of given name in XDG config folders, least important first
+pub fn config_dir_files(file_name: &str, env: impl Fn(&str) -> Option<String>) -> Vec<PathBuf> {
+    let config_home = env("XDG_CONFIG_HOME")
+        .filter(|config_home| !config_home.is_empty())
+        .map(PathBuf::from)
+        .or(env("HOME").map(|home| Path::new(&home).join(".config")));
+    let config_dirs = env("XDG_CONFIG_DIRS")
+        .filter(|config_dirs| !config_dirs.is_empty())
+        .unwrap_or("/etc/xdg".to_string());
+
+    // XDG_CONFIG_DIRS lists the most important folder first
+    let mut folders: Vec<PathBuf> = config_dirs.split(':')
+        .filter(|folder| !folder.is_empty())
+        .rev()
+        .map(PathBuf::from)
+        .collect();
+    folders.extend(config_home);
+    folders.into_iter()
+        .map(|folder| folder.join(CONFIG_DIR_NAME).join(file_name))
+        .filter(|file_path| file_path.is_file())
+        .collect()
+}
+
+/// A source folder of downloads organized into a target library
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Library {
+    pub source: String,
+    pub target: String,
+    /// Defaults to a hidden file in target directory
+    pub journal_file: Option<String>,
+}
+
+impl Library {
+    pub fn journal_path(&self) -> PathBuf {
+        match &self.journal_file {
+            Some(journal_file) => PathBuf::from(journal_file),
+            None => Path::new(&self.target).join(journal::JOURNAL_FILE_NAME),
+        }
+    }
+}
+
+/// Settings of a run, read from the config file then overridden by env vars and command line flags
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub libraries: Vec<Library>,
+    pub link_mode: LinkMode,
+    pub naming: Naming,
+    pub split_cour: SplitCour,
+    /// Episode counts of shows released with absolute numbers
+    pub episode_counts_file: Option<String>,
+    /// Central overrides by source folder name
+    pub overrides_file: Option<String>,
+    /// Filter words replacing the shipped ones
+    pub filter_words_file: Option<String>,
+    /// Entries added to and removed from the filter words, after the ones in config folders
+    pub filter_words: FilterWordsChanges,
+    pub file_extensions_file: Option<String>,
+    /// Glob patterns of source folders and files to leave out
+    pub ignore: Vec<String>,
+    /// One of off, error, warn, info, debug, trace, RUST_LOG wins over it
+    pub log_level: Option<String>,
+}
+
+/// Config keys overridable by env var and command line flag.
+/// Library keys override the single configured library, or make one up when none is configured.
+const SETTINGS: [(&str, &str, &str); 14] = [
+    ("source", "SOURCE_DIR", "source"),
+    ("target", "TARGET_DIR", "target"),
+    ("journal_file", "JOURNAL_FILE", "journal-file"),
+    ("link_mode", "LINK_MODE", "link-mode"),
+    ("naming.profile", "NAMING_PROFILE", "naming-profile"),
+    ("naming.default_subtitle_language", "DEFAULT_SUBTITLE_LANGUAGE", "default-subtitle-language"),
+    ("naming.template", "NAMING_TEMPLATE", "naming-template"),
+    ("naming.numbering", "NUMBERING", "numbering"),
+    ("split_cour", "SPLIT_COUR", "split-cour"),
+    ("episode_counts_file", "EPISODE_COUNTS_FILE", "episode-counts-file"),
+    ("overrides_file", "OVERRIDES_FILE", "overrides-file"),
+    ("filter_words_file", "FILTER_WORDS_FILE", "filter-words-file"),
+    ("file_extensions_file", "FILE_EXTENSIONS_FILE", "file-extensions-file"),
+    ("log_level", "LOG_LEVEL", "log-level"),
+];
+
+/// Parse a setting value, naming where it came from when invalid
+fn parse_setting<T: FromStr>(value: &str, origin: &str, expecting: &str) -> Result<T, ()> {
+    match value.parse() {
+        Ok(value) => Ok(value),
+        Err(_) => {
+            error!("Invalid {} {}, expecting one of {}.", &origin, &value, &expecting);
+            Err(())
+        }
+    }
+}
+
+/// Command line flags by name without dashes, in given order
+type Flags = Vec<(String, String)>;
+
+/// Split command line arguments into flags like "--link-mode copy" or "--link-mode=copy" and positional arguments
+fn parse_flags(args: Vec<String>) -> Result<(Flags, Vec<String>), ()> {
+    let mut flags = Vec::new();
+    let mut positional = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let flag = match arg.strip_prefix("--") {
+            Some(flag) => flag.to_string(),
+            None => {
+                positional.push(arg);
+                continue;
+            }
+        };
+
+        let (name, value) = match flag.split_once('=') {
+            Some((name, value)) => (name.to_string(), value.to_string()),
+            None => match args.next() {
+                Some(value) => (flag, value),
+                None => {
+                    error!("Missing value of --{}", &flag);
+                    return Err(());
+                }
+            },
+        };
+        if name != "config" && !SETTINGS.iter().any(|(_, _, setting_flag)| *setting_flag == name) {
+            let known_flags: Vec<String> = SETTINGS.iter().map(|(_, _, setting_flag)| format!("--{}", setting_flag)).collect();
+            error!("Unknown option --{}, expecting --config or one of {}", &name, known_flags.join(", "));
+            return Err(());
+        }
+        flags.push((name, value));
+    }
+    Ok((flags, positional))
+}
+
+impl Config {
+    pub fn load(file_path: &str) -> Result<Config, ()> {
+        let config_string = match fs::read_to_string(file_path) {
+            Ok(content) => content,
+            Err(e) => {
+                error!("Failed to load config, please check {}, due to {}", &file_path, &e);
+                return Err(());
+            }
+        };
+
+        // Errors name the offending key, like "link_mode: unknown variant `link`"
+        match serde_yaml::from_str::<Config>(&config_string) {
+            Ok(content) => {
+                info!("Load config {} successfully.", &file_path);
+                Ok(content)
+            },
+            Err(err) => {
+                error!("Invalid config {}: {}", &file_path, err);
+                Err(())
+            }
+        }
+    }
+
+    /// Layer config file, env vars looked up by env and command line flags, returns the positional arguments left.
+    /// Config file comes from --config, CONFIG_FILE or anime-organizer.yaml in the working directory.
+    pub fn resolve(args: Vec<String>, env: impl Fn(&str) -> Option<String>) -> Result<(Config, Vec<String>), ()> {
+        let (flags, positional) = parse_flags(args)?;
+
+        let config_file = flags.iter()
+            .find(|(name, _)| name == "config")
+            .map(|(_, value)| value.clone())
+            .or(env("CONFIG_FILE"))
+            .or(Some(CONFIG_FILE_NAME.to_string()).filter(|config_file| Path::new(config_file).is_file()));
+        let mut config = match config_file {
+            Some(config_file) => Config::load(&config_file)?,
+            None => Config::default(),
+        };
+
+        for (key, env_var, _) in SETTINGS {
+            if let Some(value) = env(env_var) {
+                config.set(key, &value, env_var)?;
+            }
+        }
+        for (name, value) in flags.iter().filter(|(name, _)| name != "config") {
+            if let Some((key, _, _)) = SETTINGS.iter().find(|(_, _, setting_flag)| setting_flag == name) {
+                config.set(key, value, &format!("--{}", name))?;
+            }
+        }
+
+        config.validate()?;
+        Ok((config, positional))
+    }
+
+    /// Override a config key with a value from env var or command line flag
+    fn set(&mut self, key: &str, value: &str, origin: &str) -> Result<(), ()> {
+        info!("{}={}", &origin, &value);
+        match key {
+            "source" | "target" | "journal_file" => {
+                if self.libraries.len() > 1 {
+                    error!("libraries: {} cannot tell which of {} configured libraries to override, set {} per library in config file", &origin, self.libraries.len(), &key);
+                    return Err(());
+                }
+                if self.libraries.is_empty() {
+                    self.libraries.push(Library::default());
+                }
+                let library = &mut self.libraries[0];
+                match key {
+                    "source" => library.source = value.to_string(),
+                    "target" => library.target = value.to_string(),
+                    _ => library.journal_file = Some(value.to_string()),
+                }
+            },
+            "link_mode" => self.link_mode = parse_setting(value, origin, "hardlink, symlink-relative, symlink-absolute, reflink, copy, move")?,
+            "naming.profile" => self.naming.profile = parse_setting(value, origin, "jellyfin, plex, kodi, emby")?,
+            "naming.default_subtitle_language" => self.naming.default_subtitle_language = Some(value.to_string()),
+            "naming.template" => self.naming.template = Some(value.to_string()),
+            "naming.numbering" => self.naming.numbering = parse_setting(value, origin, "seasonal, absolute")?,
+            "split_cour" => self.split_cour = parse_setting(value, origin, "merge, separate")?,
+            "episode_counts_file" => self.episode_counts_file = Some(value.to_string()),
+            "overrides_file" => self.overrides_file = Some(value.to_string()),
+            "filter_words_file" => self.filter_words_file = Some(value.to_string()),
+            "file_extensions_file" => self.file_extensions_file = Some(value.to_string()),
+            "log_level" => {
+                parse_setting::<LevelFilter>(value, origin, "off, error, warn, info, debug, trace")?;
+                self.log_level = Some(value.to_string());
+            },
+            _ => {
+                error!("Unknown config key {} from {}", &key, &origin);
+                return Err(());
+            }
+        }
+        Ok(())
+    }
+
+    /// Check values the file format cannot, every error names the offending key
+    pub fn validate(&self) -> Result<(), ()> {
+        let mut result = Ok(());
+        if let Some(template) = &self.naming.template {
+            if naming::check_template(template).is_err() {
+                error!("naming.template: invalid template {}", &template);
+                result = Err(());
+            }
+        }
+        if let Some(log_level) = &self.log_level {
+            if log_level.parse::<LevelFilter>().is_err() {
+                error!("log_level: unknown level {}, expecting one of off, error, warn, info, debug, trace", &log_level);
+                result = Err(());
+            }
+        }
+        result
+    }
+
+    /// Check source and target folders, only needed by commands working on libraries
+    pub fn validate_libraries(&self) -> Result<(), ()> {
+        let mut result = Ok(());
+        if self.libraries.is_empty() {
+            error!("libraries: no source and target folders, set them in config file, by SOURCE_DIR and TARGET_DIR or by --source and --target");
+            result = Err(());
+        }
+        for (index, library) in self.libraries.iter().enumerate() {
+            if library.source.is_empty() {
+                error!("libraries[{}].source: missing source folder", &index);
+                result = Err(());
+            } else if !Path::new(&library.source).is_dir() {
+                error!("libraries[{}].source: {} is not a folder", &index, &library.source);
+                result = Err(());
+            }
+            if library.target.is_empty() {
+                error!("libraries[{}].target: missing target folder", &index);
+                result = Err(());
+            }
+        }
+        result
+    }
+
+    /// Log level from config, only errors when not set
+    pub fn log_level(&self) -> LevelFilter {
+        self.log_level.as_ref()
+            .and_then(|log_level| log_level.parse().ok())
+            .unwrap_or(LevelFilter::Error)
+    }
+
+    /// Shipped or configured filter words, changed by filter_words.yaml in config folders found through env, then by the config file
+    pub fn filter_words(&self, env: impl Fn(&str) -> Option<String>) -> Result<FilterWords, ()> {
+        let mut filter_words = match &self.filter_words_file {
+            Some(filter_words_file) => FilterWords::load_from(filter_words_file)?,
+            None => FilterWords::load(),
+        };
+        for file_path in config_dir_files(FILTER_WORDS_FILE_NAME, env) {
+            filter_words.apply(&FilterWordsChanges::load(&file_path)?, &file_path.to_string_lossy())?;
+        }
+        filter_words.apply(&self.filter_words, "filter_words of config file")?;
+        Ok(filter_words)
+    }
+
+    /// Journal of the library with given target directory
+    pub fn journal_path(&self, target_directory: &Path) -> PathBuf {
+        match self.libraries.iter().find(|library| Path::new(&library.target) == target_directory) {
+            Some(library) => library.journal_path(),
+            None => target_directory.join(journal::JOURNAL_FILE_NAME),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+
+    use crate::materialize::LinkMode;
+    use crate::naming::{NamingProfile, Numbering};
+    use crate::series::{explain_name, FileExtensions, SplitCour};
+    use super::{config_dir_files, Config, CONFIG_DIR_NAME, FILTER_WORDS_FILE_NAME};
+
+    #[test]
+    fn config_layering() {
+        // Setup
+        let _ = env_logger::builder().is_test(true).try_init();
+        let test_directory = std::env::temp_dir().join(format!("anime-organizer-rs-config-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&test_directory);
+        fs::create_dir_all(test_directory.join("anime")).unwrap();
+        fs::create_dir_all(test_directory.join("movies")).unwrap();
+        let config_path = test_directory.join("config.yaml");
+        let config_file = config_path.to_string_lossy().to_string();
+
+        // Several libraries in config file
+        fs::write(&config_path, format!(
+            "libraries:\n  - source: {0}/anime\n    target: {0}/library/anime\n  - source: {0}/movies\n    target: {0}/library/movies\nlink_mode: symlink-relative\nnaming:\n  profile: plex\nignore: ['*.!qB']\n",
+            test_directory.display(),
+        )).unwrap();
+        let no_env = |_: &str| None;
+        let (config, positional) = Config::resolve(vec!["--config".to_string(), config_file.clone(), "plan".to_string()], no_env).unwrap();
+        assert_eq!(config.libraries.len(), 2);
+        assert_eq!(config.link_mode, LinkMode::SymlinkRelative);
+        assert_eq!(config.naming.profile, NamingProfile::Plex);
+        assert_eq!(config.ignore, ["*.!qB"]);
+        assert_eq!(positional, ["plan"]);
+
+        // Env vars win over config file, command line flags win over both
+        let env = HashMap::from([
+            ("CONFIG_FILE", config_file.clone()),
+            ("LINK_MODE", "move".to_string()),
+            ("NUMBERING", "absolute".to_string()),
+        ]);
+        let env = |name: &str| env.get(name).cloned();
+        let (config, _) = Config::resolve(vec!["--link-mode".to_string(), "copy".to_string()], env).unwrap();
+        assert_eq!(config.link_mode, LinkMode::Copy);
+        assert_eq!(config.naming.numbering, Numbering::Absolute);
+        assert_eq!(config.naming.profile, NamingProfile::Plex);
+        assert!(Config::resolve(vec!["--link-mode=link".to_string()], env).is_err());
+        assert!(Config::resolve(vec!["--linkmode=copy".to_string()], env).is_err());
+
+        // Library keys from env vars are refused when several libraries are configured
+        for (env_var, value) in [("JOURNAL_FILE", "journal.jsonl"), ("SOURCE_DIR", "anime"), ("TARGET_DIR", "library")] {
+            let library_env = HashMap::from([
+                ("CONFIG_FILE", config_file.clone()),
+                (env_var, value.to_string()),
+            ]);
+            assert!(Config::resolve(Vec::new(), |name: &str| library_env.get(name).cloned()).is_err());
+        }
+
+        // Or make up a single library without config file
+        let single_env = HashMap::from([
+            ("SOURCE_DIR", test_directory.join("anime").to_string_lossy().to_string()),
+            ("TARGET_DIR", "library".to_string()),
+        ]);
+        let (config, _) = Config::resolve(Vec::new(), |name: &str| single_env.get(name).cloned()).unwrap();
+        assert_eq!(config.libraries.len(), 1);
+        assert_eq!(config.libraries[0].target, "library");
+
+        // Unknown keys and invalid values are refused
+        fs::write(&config_path, "link_mode: link\n").unwrap();
+        assert!(Config::load(&config_file).is_err());
+        fs::write(&config_path, "libraries: []\nlinkmode: copy\n").unwrap();
+        assert!(Config::load(&config_file).is_err());
+        fs::write(&config_path, format!("libraries:\n  - source: {}/missing\n    target: library\n", test_directory.display())).unwrap();
+        assert!(Config::load(&config_file).unwrap().validate_libraries().is_err());
+
+        let _ = fs::remove_dir_all(&test_directory);
+    }
+
+    #[test]
+    fn filter_words_layering() {
+        // Setup
+        let _ = env_logger::builder().is_test(true).try_init();
+        let test_directory = std::env::temp_dir().join(format!("anime-organizer-rs-filter-words-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&test_directory);
+        let config_dirs = test_directory.join("etc");
+        let config_home = test_directory.join("home");
+        fs::create_dir_all(config_dirs.join(CONFIG_DIR_NAME)).unwrap();
+        fs::create_dir_all(config_home.join(CONFIG_DIR_NAME)).unwrap();
+        let config_path = test_directory.join("config.yaml");
+
+        // System wide list adds groups, user list removes one of them, config file adds a regex
+        fs::write(config_dirs.join(CONFIG_DIR_NAME).join(FILTER_WORDS_FILE_NAME), "cc_group:\n  add: [MyFansub, OtherSub]\n").unwrap();
+        fs::write(config_home.join(CONFIG_DIR_NAME).join(FILTER_WORDS_FILE_NAME), "cc_group:\n  remove: [othersub]\n").unwrap();
+        fs::write(&config_path, "filter_words:\n  meta_tag:\n    add:\n      - regex: 'Hi\\d+P?'\n").unwrap();
+        let env = HashMap::from([
+            ("XDG_CONFIG_DIRS", config_dirs.to_string_lossy().to_string()),
+            ("XDG_CONFIG_HOME", config_home.to_string_lossy().to_string()),
+        ]);
+        let env = |name: &str| env.get(name).cloned();
+        assert_eq!(config_dir_files(FILTER_WORDS_FILE_NAME, env).len(), 2);
+
+        // Run test
+        let config = Config::load(&config_path.to_string_lossy()).unwrap();
+        let filter_words = config.filter_words(env).unwrap();
+        let file_extensions = FileExtensions::load();
+        let explanation = explain_name("Frieren [MyFansub][Hi10P]", &filter_words, &file_extensions, SplitCour::default());
+        assert_eq!(explanation.series_name.as_deref(), Some("Frieren"));
+        assert_eq!(explanation.release.groups, ["MyFansub"]);
+        let explanation = explain_name("Frieren [OtherSub]", &filter_words, &file_extensions, SplitCour::default());
+        assert_eq!(explanation.series_name.as_deref(), Some("Frieren [OtherSub]"));
+
+        // Invalid regex entries are refused
+        fs::write(&config_path, "filter_words:\n  cc_group:\n    add:\n      - regex: '[Group'\n").unwrap();
+        assert!(Config::load(&config_path.to_string_lossy()).unwrap().filter_words(env).is_err());
+
+        let _ = fs::remove_dir_all(&test_directory);
+    }
+}
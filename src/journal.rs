@@ -0,0 +1,339 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use log::debug;
+use log::error;
+use log::info;
+use log::warn;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::materialize::LinkMode;
+use crate::plan::PlanEntry;
+
+/// Default journal file name, placed in the target directory
+pub const JOURNAL_FILE_NAME: &str = ".anime-organizer-journal.jsonl";
+
+/// Append only record of every file placed in the target library, one JSON object per line
+pub struct Journal {
+    file_path: PathBuf,
+    run_id: String,
+}
+
+/// A single file system action performed by the organizer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub run_id: String,
+    /// Seconds since UNIX epoch
+    pub timestamp: u64,
+    pub series: String,
+    pub action: LinkMode,
+    pub source: PathBuf,
+    pub target: PathBuf,
+    pub inode: Option<u64>,
+    pub size: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or_default()
+}
+
+impl Journal {
+    /// Start a new run, appending to journal file
+    pub fn open(file_path: &Path) -> Journal {
+        let run_id = format!("{}-{}", now(), std::process::id());
+        info!("Journal {}, run id {}", file_path.display(), &run_id);
+        Journal {
+            file_path: file_path.to_path_buf(),
+            run_id,
+        }
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Source and target of every action still in effect from earlier runs
+    pub fn placed(&self) -> HashSet<(PathBuf, PathBuf)> {
+        if !self.file_path.exists() {
+            return HashSet::new();
+        }
+        Journal::load(&self.file_path).unwrap_or_default().into_iter()
+            .map(|journal_entry| (journal_entry.source, journal_entry.target))
+            .collect()
+    }
+
+    /// Append an action performed for a plan entry
+    pub fn record(&self, entry: &PlanEntry, action: LinkMode) -> Result<(), ()> {
+        let metadata = fs::symlink_metadata(&entry.target).ok();
+        let journal_entry = JournalEntry {
+            run_id: self.run_id.clone(),
+            timestamp: now(),
+            series: entry.series.clone(),
+            action,
+            source: entry.source.clone(),
+            target: entry.target.clone(),
+            inode: metadata.as_ref().and_then(inode),
+            size: metadata.map(|metadata| metadata.len()).unwrap_or_default(),
+        };
+
+        let line = match serde_json::to_string(&journal_entry) {
+            Ok(line) => line,
+            Err(e) => {
+                error!("Failed to serialize journal entry, due to {}", &e);
+                return Err(());
+            }
+        };
+
+        if let Some(parent) = self.file_path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        let file = fs::OpenOptions::new().create(true).append(true).open(&self.file_path);
+        match file.and_then(|mut file| writeln!(file, "{}", line)) {
+            Ok(_) => {
+                debug!("Journaled {} -> {}", entry.source.display(), entry.target.display());
+                Ok(())
+            },
+            Err(e) => {
+                error!("Failed to write journal {}, due to {}", self.file_path.display(), &e);
+                Err(())
+            }
+        }
+    }
+
+    pub fn load(file_path: &Path) -> Result<Vec<JournalEntry>, ()> {
+        let content = match fs::read_to_string(file_path) {
+            Ok(content) => content,
+            Err(e) => {
+                error!("Failed to read journal {}, due to {}", file_path.display(), &e);
+                return Err(());
+            }
+        };
+
+        let mut result = Vec::new();
+        for (line_number, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(line) {
+                Ok(entry) => result.push(entry),
+                Err(e) => warn!("Skipping malformed journal line {}, due to {}", line_number + 1, &e),
+            }
+        }
+        Ok(result)
+    }
+
+    /// Whether the journal holds actions of a run (latest run when not given), optionally limited to one series
+    pub fn contains(file_path: &Path, run_id: Option<&str>, series: Option<&str>) -> bool {
+        let entries = Journal::load(file_path).unwrap_or_default();
+        match selected_run_id(&entries, run_id) {
+            Some(run_id) => entries.iter().any(|entry| is_selected(entry, &run_id, series)),
+            None => false,
+        }
+    }
+
+    /// Undo every action of a run (latest run when not given), optionally limited to one series.
+    /// Successfully undone entries are removed from the journal. Returns the number of failures.
+    pub fn rollback(file_path: &Path, target_directory: &Path, run_id: Option<&str>, series: Option<&str>) -> Result<usize, ()> {
+        let entries = Journal::load(file_path)?;
+        let run_id = match selected_run_id(&entries, run_id) {
+            Some(run_id) => run_id,
+            None => {
+                warn!("Journal {} is empty, nothing to roll back.", file_path.display());
+                return Ok(0);
+            }
+        };
+        if !entries.iter().any(|entry| is_selected(entry, &run_id, series)) {
+            match series {
+                Some(series) => error!("No journal entries for series {} in run {} in {}", &series, &run_id, file_path.display()),
+                None => error!("No journal entries for run {} in {}", &run_id, file_path.display()),
+            }
+            return Err(());
+        }
+        info!("Rolling back run {}", &run_id);
+
+        let mut failure_count = 0;
+        let mut remaining = Vec::new();
+        let mut undone = Vec::new();
+        for entry in entries.into_iter().rev() {
+            if !is_selected(&entry, &run_id, series) {
+                remaining.push(entry);
+                continue;
+            }
+
+            match undo(&entry) {
+                Ok(_) => {
+                    remove_empty_folders(&entry.target, target_directory);
+                    undone.push(entry);
+                },
+                Err(_) => {
+                    failure_count += 1;
+                    remaining.push(entry);
+                }
+            }
+        }
+        remaining.reverse();
+        info!("Rolled back {} actions with {} failures.", undone.len(), &failure_count);
+
+        // Keep only the actions which are still in effect
+        let mut content = String::new();
+        for entry in &remaining {
+            match serde_json::to_string(entry) {
+                Ok(line) => {
+                    content.push_str(&line);
+                    content.push('\n');
+                },
+                Err(e) => error!("Failed to serialize journal entry, due to {}", &e),
+            }
+        }
+        if let Err(e) = fs::write(file_path, content) {
+            error!("Failed to rewrite journal {}, due to {}", file_path.display(), &e);
+            return Err(());
+        }
+
+        Ok(failure_count)
+    }
+}
+
+/// Run given, or the latest one in the journal
+fn selected_run_id(entries: &[JournalEntry], run_id: Option<&str>) -> Option<String> {
+    match run_id {
+        Some(run_id) => Some(run_id.to_string()),
+        None => entries.last().map(|entry| entry.run_id.clone()),
+    }
+}
+
+/// Whether an entry belongs to the run, and to the series when given
+fn is_selected(entry: &JournalEntry, run_id: &str, series: Option<&str>) -> bool {
+    entry.run_id == run_id && series.is_none_or(|series| entry.series == series)
+}
+
+#[cfg(unix)]
+fn inode(metadata: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// Revert a single action, refusing to touch targets which changed since they were created
+fn undo(entry: &JournalEntry) -> Result<(), ()> {
+    let metadata = match fs::symlink_metadata(&entry.target) {
+        Ok(metadata) => metadata,
+        Err(_) => {
+            info!("{} is already gone.", entry.target.display());
+            return Ok(());
+        }
+    };
+
+    if inode(&metadata) != entry.inode || metadata.len() != entry.size {
+        warn!("{} changed since it was created, leaving it in place.", entry.target.display());
+        return Err(());
+    }
+
+    match entry.action {
+        LinkMode::Move => match LinkMode::Move.materialize(&entry.target, &entry.source) {
+            Ok(Some(_)) => {
+                info!("Moved {} back to {}", entry.target.display(), entry.source.display());
+                Ok(())
+            },
+            _ => {
+                error!("Failed to move {} back to {}", entry.target.display(), entry.source.display());
+                Err(())
+            }
+        },
+        _ => match fs::remove_file(&entry.target) {
+            Ok(_) => {
+                info!("Removed {}", entry.target.display());
+                Ok(())
+            },
+            Err(e) => {
+                error!("Failed to remove {}, due to {}", entry.target.display(), &e);
+                Err(())
+            }
+        },
+    }
+}
+
+/// Remove folders left empty by a rollback, up to but excluding target directory
+fn remove_empty_folders(target: &Path, target_directory: &Path) {
+    let mut folder = target.parent();
+    while let Some(path) = folder {
+        if path == target_directory || !path.starts_with(target_directory) || fs::remove_dir(path).is_err() {
+            break;
+        }
+        debug!("Removed empty folder {}", path.display());
+        folder = path.parent();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::materialize::LinkMode;
+    use crate::plan::PlanEntry;
+    use super::Journal;
+
+    #[test]
+    fn journal_rollback() {
+        // Setup
+        let _ = env_logger::builder().is_test(true).try_init();
+        let test_directory = std::env::temp_dir().join(format!("anime-organizer-rs-journal-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&test_directory);
+        let target_directory = test_directory.join("target");
+        let journal_path = target_directory.join(super::JOURNAL_FILE_NAME);
+
+        // Organize two series in one run
+        let journal = Journal::open(&journal_path);
+        for (series, action) in [("Engage Kiss", LinkMode::Hardlink), ("Freezing", LinkMode::Move)] {
+            let source = test_directory.join(format!("source/{}.mkv", series));
+            fs::create_dir_all(source.parent().unwrap()).unwrap();
+            fs::write(&source, series).unwrap();
+
+            let entry = PlanEntry {
+                series: series.to_string(),
+                source,
+                target: target_directory.join(format!("{}/Season 01/{} - S01E01.mkv", series, series)),
+                action,
+                confidence: 1.0,
+                reason: "".to_string(),
+                rule: None,
+                candidates: Vec::new(),
+                review: false,
+                overridden: Vec::new(),
+            };
+            let used_action = action.materialize(&entry.source, &entry.target).unwrap().unwrap();
+            journal.record(&entry, used_action).unwrap();
+        }
+
+        // Roll back a single series
+        assert_eq!(Journal::rollback(&journal_path, &target_directory, Some(journal.run_id()), Some("Freezing")).unwrap(), 0);
+        assert!(!target_directory.join("Freezing").exists());
+        assert!(test_directory.join("source/Freezing.mkv").exists());
+        assert!(target_directory.join("Engage Kiss/Season 01/Engage Kiss - S01E01.mkv").exists());
+        assert_eq!(Journal::load(&journal_path).unwrap().len(), 1);
+
+        // An unknown run or series selects nothing and leaves the journal as it is
+        assert!(Journal::rollback(&journal_path, &target_directory, Some("0-0"), None).is_err());
+        assert!(Journal::rollback(&journal_path, &target_directory, None, Some("Freezing")).is_err());
+        assert!(!Journal::contains(&journal_path, None, Some("Freezing")));
+        assert!(Journal::contains(&journal_path, Some(journal.run_id()), Some("Engage Kiss")));
+        assert_eq!(Journal::load(&journal_path).unwrap().len(), 1);
+
+        // Roll back the rest of the latest run
+        assert_eq!(Journal::rollback(&journal_path, &target_directory, None, None).unwrap(), 0);
+        assert!(!target_directory.join("Engage Kiss").exists());
+        assert!(test_directory.join("source/Engage Kiss.mkv").exists());
+        assert!(Journal::load(&journal_path).unwrap().is_empty());
+
+        let _ = fs::remove_dir_all(&test_directory);
+    }
+}
@@ -1,3 +1,14 @@
+// Errors are logged where they happen, callers only need to know something failed
+#![allow(clippy::result_unit_err)]
+
+pub mod config;
+pub mod journal;
+pub mod materialize;
+pub mod naming;
+pub mod overrides;
+pub mod plan;
+pub mod series;
+
 use log::debug;
 use log::error;
 use log::info;
@@ -5,14 +16,14 @@ use log::info;
 
 pub fn load_env_var(var_name: &str) -> Result<String, std::env::VarError> {
     debug!("Loading env var: {}", &var_name);
-    match std::env::var(&var_name) {
+    match std::env::var(var_name) {
         Ok(var_value) => {
             info!("{}={}", &var_name, &var_value);
-            return Ok(var_value)
+            Ok(var_value)
         },
         Err(e) => {
-            error!("Failed to load TARGET_DIR env var. Please check .env file.");
-            return Err(e)
+            error!("Failed to load {} env var, due to {}. Please check .env file.", &var_name, &e);
+            Err(e)
         },
-    };
+    }
 }
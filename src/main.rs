@@ -1,40 +1,349 @@
-mod series;
 mod tests;
 
-use std::fs;
+use anime_organizer_rs::config;
+use anime_organizer_rs::journal;
+use anime_organizer_rs::materialize;
+use anime_organizer_rs::overrides;
+use anime_organizer_rs::plan;
+use anime_organizer_rs::series;
 
-use anime_organizer_rs::load_env_var;
+use config::Config;
+use config::Library;
 use dotenvy::dotenv;
-use log::debug;
+use journal::Journal;
+use overrides::Overrides;
+use plan::Plan;
+use plan::Scan;
+use series::EpisodeCounts;
+use series::FileExtensions;
+use series::FilterWords;
 use log::error;
 use log::info;
 use log::warn;
+use log::LevelFilter;
+use std::fmt;
+use std::io::ErrorKind;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
 
+const USAGE: &str = "Usage: anime-organizer-rs [--config FILE] [--OPTION VALUE]... {scan | plan [PLAN_FILE] | apply [PLAN_FILE] | undo [RUN_ID|latest] [SERIES] | explain NAME... | check}";
 
+/// Every file was handled
+const EXIT_SUCCESS: i32 = 0;
+/// Some files failed or could not be resolved, the rest was handled
+const EXIT_PARTIAL_FAILURE: i32 = 1;
+/// Invalid config, arguments or input files, nothing was done
+const EXIT_CONFIG_ERROR: i32 = 2;
 
 fn main() {
     // Load .env file
     dotenv().ok(); // Need to be loaded before logger init for setting env vars up.
 
-    // Init logger
-    env_logger::init();
+    // Init logger, RUST_LOG wins over configured log level
+    let rust_log = std::env::var("RUST_LOG").is_ok();
+    if rust_log {
+        env_logger::init();
+    } else {
+        env_logger::Builder::new().filter_level(LevelFilter::Trace).init();
+        log::set_max_level(LevelFilter::Warn);
+    }
 
-    // TODO: Check source directory
-    let source_directory = load_env_var(&"SOURCE_DIR").unwrap();
+    let (config, args) = match Config::resolve(std::env::args().skip(1).collect(), |name| std::env::var(name).ok()) {
+        Ok(resolved) => resolved,
+        Err(_) => {
+            error!("{}", USAGE);
+            std::process::exit(EXIT_CONFIG_ERROR);
+        }
+    };
+    if !rust_log {
+        log::set_max_level(config.log_level());
+    }
 
-    // Check and create target directory
-    debug!("Checking and creating folder for target mapping.");
-    let target_directory = load_env_var(&"TARGET_DIR").unwrap();
-    series::create_folder(&target_directory);
-    debug!("Finish creating folder {} for target mapping.", target_directory);
+    // Files are only touched on an explicit command
+    let command = match args.first() {
+        Some(command) => command.as_str(),
+        None => {
+            error!("Missing command. {}", USAGE);
+            std::process::exit(EXIT_CONFIG_ERROR);
+        }
+    };
+    let needs_libraries = match command {
+        "explain" => false,
+        "apply" => args.get(1).is_none(),
+        _ => true,
+    };
+    if needs_libraries && config.validate_libraries().is_err() {
+        std::process::exit(EXIT_CONFIG_ERROR);
+    }
 
-    // Iterate through source directory to resolve each anime series
-    
-    let paths = match fs::read_dir(&source_directory) {
-        Ok(paths) => paths,
-        Err(e) => {
-            error!("Failed open {}, due to {}", &source_directory, &e);
-            panic!();
+    let failure_count = match command {
+        "scan" => scan(&config),
+        "plan" => {
+            // Dry run, only write the plans out, numbered by library when there are several
+            let plans = build_plans(&config);
+            let plan_count = plans.len();
+            let mut failure_count = 0;
+            for (index, (_, plan)) in plans.iter().enumerate() {
+                let plan_file = match args.get(1) {
+                    Some(plan_file) if plan_count > 1 => Some(plan_file_path(plan_file, index + 1)),
+                    plan_file => plan_file.cloned(),
+                };
+                if plan.save(plan_file.as_deref()).is_err() {
+                    failure_count += 1;
+                }
+            }
+            failure_count
+        },
+        "apply" => match args.get(1) {
+            // Apply an existing plan, which may have been edited by hand
+            Some(plan_file) => match Plan::load(plan_file) {
+                Ok(plan) => apply_plan(&plan, &config.journal_path(&plan.target_directory)),
+                Err(_) => std::process::exit(EXIT_CONFIG_ERROR),
+            },
+            None => {
+                let mut failure_count = 0;
+                for (library, plan) in build_plans(&config) {
+                    failure_count += apply_plan(&plan, &library.journal_path());
+                }
+                failure_count
+            }
+        },
+        "undo" => {
+            // Roll back a run, latest one by default, optionally a single series of it
+            let run_id = args.get(1).map(String::as_str).filter(|run_id| *run_id != "latest");
+            undo(&config, run_id, args.get(2).map(String::as_str))
+        },
+        "explain" => {
+            if args.len() < 2 {
+                error!("Missing name to explain. {}", USAGE);
+                std::process::exit(EXIT_CONFIG_ERROR);
+            }
+            explain(&config, &args[1..])
+        },
+        "check" => check(&config),
+        _ => {
+            error!("Unknown command {}. {}", &command, USAGE);
+            std::process::exit(EXIT_CONFIG_ERROR);
         }
     };
+
+    match failure_count {
+        0 => std::process::exit(EXIT_SUCCESS),
+        _ => std::process::exit(EXIT_PARTIAL_FAILURE),
+    }
+}
+
+/// Print a line of output, a reader closing early like `plan | head` ends the run normally
+fn print_line(line: impl fmt::Display) {
+    if let Err(e) = writeln!(std::io::stdout().lock(), "{}", line) {
+        if e.kind() == ErrorKind::BrokenPipe {
+            std::process::exit(EXIT_SUCCESS);
+        }
+        error!("Failed to write output, due to {}", &e);
+        std::process::exit(EXIT_PARTIAL_FAILURE);
+    }
+}
+
+/// Files shared by every library, loaded once
+struct Resources {
+    episode_counts: EpisodeCounts,
+    overrides: Overrides,
+    filter_words: FilterWords,
+    file_extensions: FileExtensions,
+}
+
+/// Load files named in config, exits on config errors
+fn load_resources(config: &Config) -> Resources {
+    // Episode counts of shows released with absolute numbers
+    let episode_counts = match &config.episode_counts_file {
+        Some(episode_counts_file) => EpisodeCounts::load(episode_counts_file),
+        None => Ok(EpisodeCounts::default()),
+    };
+
+    // Central overrides by source folder name, sidecar files in source folders win over them
+    let overrides = match &config.overrides_file {
+        Some(overrides_file) => Overrides::load(overrides_file),
+        None => Ok(Overrides::default()),
+    };
+
+    // Shipped filter words changed by the user, shipped lists are built into the binary
+    let filter_words = config.filter_words(|name| std::env::var(name).ok());
+    let file_extensions = match &config.file_extensions_file {
+        Some(file_extensions_file) => FileExtensions::load_from(file_extensions_file),
+        None => Ok(FileExtensions::load()),
+    };
+    match (episode_counts, overrides, filter_words, file_extensions) {
+        (Ok(episode_counts), Ok(overrides), Ok(filter_words), Ok(file_extensions)) => Resources {
+            episode_counts,
+            overrides,
+            filter_words,
+            file_extensions,
+        },
+        _ => std::process::exit(EXIT_CONFIG_ERROR),
+    }
+}
+
+/// Plan file of one of several libraries, like plan.2.json for plan.json
+fn plan_file_path(plan_file: &str, library_number: usize) -> String {
+    let path = Path::new(plan_file);
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}.{}.{}", &stem, &library_number, extension.to_string_lossy()),
+        None => format!("{}.{}", &stem, &library_number),
+    };
+    path.with_file_name(file_name).to_string_lossy().to_string()
+}
+
+/// List the series found in every source folder, returns the number of unresolved folders and files
+fn scan(config: &Config) -> usize {
+    let resources = load_resources(config);
+    let mut failure_count = 0;
+    for library in &config.libraries {
+        let scan = match Scan::new(&library.source, config, &resources.episode_counts, &resources.overrides, &resources.filter_words, &resources.file_extensions) {
+            Ok(scan) => scan,
+            Err(_) => std::process::exit(EXIT_CONFIG_ERROR),
+        };
+        print_line(&library.source);
+        for series in &scan.series {
+            print_line(series);
+            failure_count += series.unresolved().len();
+        }
+        for path in &scan.unresolved {
+            print_line(format_args!("Unresolved {}", path.display()));
+        }
+        failure_count += scan.unresolved.len();
+    }
+    failure_count
+}
+
+/// Resolve every series of every library into a plan
+fn build_plans(config: &Config) -> Vec<(Library, Plan)> {
+    info!("Link mode: {}", &config.link_mode);
+    info!("Naming profile: {}, numbering: {}", &config.naming.profile, &config.naming.numbering);
+    if let Some(template) = &config.naming.template {
+        info!("Naming template: {}", &template);
+    }
+    info!("Split cour: {}", &config.split_cour);
+
+    // Iterate through source directories to resolve each anime series
+    let resources = load_resources(config);
+    let mut result = Vec::new();
+    for library in &config.libraries {
+        match Plan::build(&library.source, &library.target, config, &resources.episode_counts, &resources.overrides, &resources.filter_words, &resources.file_extensions) {
+            Ok(plan) => result.push((library.clone(), plan)),
+            Err(_) => std::process::exit(EXIT_CONFIG_ERROR),
+        }
+    }
+    result
+}
+
+/// Apply a plan, returns the number of failed and unresolved entries
+fn apply_plan(plan: &Plan, journal_path: &Path) -> usize {
+    let journal = Journal::open(journal_path);
+    let failure_count = plan.apply(&journal) + plan.unresolved.len();
+    match failure_count {
+        0 => info!("Finish organizing {}, run id {}.", plan.source_directory.display(), journal.run_id()),
+        _ => warn!("Finish organizing {} with {} failures, run id {}.", plan.source_directory.display(), &failure_count, journal.run_id()),
+    }
+    failure_count
+}
+
+/// Roll back a run in the journal of every library, returns the number of failures
+fn undo(config: &Config, run_id: Option<&str>, series: Option<&str>) -> usize {
+    let mut failure_count = 0;
+    let mut journal_count = 0;
+    for library in &config.libraries {
+        let journal_path = library.journal_path();
+        if !journal_path.exists() {
+            info!("No journal {}, nothing to roll back.", journal_path.display());
+            continue;
+        }
+        // Every library has runs of its own, a run or series is usually in one of them only
+        if config.libraries.len() > 1 && !Journal::contains(&journal_path, run_id, series) {
+            info!("Nothing to roll back in {}", journal_path.display());
+            continue;
+        }
+        journal_count += 1;
+        match Journal::rollback(&journal_path, Path::new(&library.target), run_id, series) {
+            Ok(0) => info!("Finish rolling back {}.", &library.target),
+            Ok(library_failure_count) => {
+                warn!("Finish rolling back {} with {} failures.", &library.target, &library_failure_count);
+                failure_count += library_failure_count;
+            },
+            Err(_) => failure_count += 1,
+        }
+    }
+    if config.libraries.len() > 1 && journal_count == 0 {
+        match (run_id, series) {
+            (Some(run_id), Some(series)) => error!("No journal entries for series {} in run {} in any library", &series, &run_id),
+            (Some(run_id), None) => error!("No journal entries for run {} in any library", &run_id),
+            (None, Some(series)) => error!("No journal entries for series {} in the latest run of any library", &series),
+            (None, None) => return failure_count,
+        }
+        failure_count += 1;
+    }
+    failure_count
+}
+
+/// Print how file or folder names are taken apart
+fn explain(config: &Config, names: &[String]) -> usize {
+    let resources = load_resources(config);
+    for name in names {
+        let explanation = series::explain_name(name, &resources.filter_words, &resources.file_extensions, config.split_cour);
+        match serde_yaml::to_string(&explanation) {
+            Ok(content) => print_line(content),
+            Err(e) => {
+                error!("Failed to serialize explanation, due to {}", &e);
+                return 1;
+            }
+        }
+    }
+    0
+}
+
+/// Look for broken links, unnumbered files and missing or duplicate episodes in every target library.
+/// Returns the number of problems found, exits on missing or unreadable target folders.
+fn check(config: &Config) -> usize {
+    let resources = load_resources(config);
+    let mut failure_count = 0;
+    for library in &config.libraries {
+        if !Path::new(&library.target).is_dir() {
+            error!("Target folder {} does not exist", &library.target);
+            std::process::exit(EXIT_CONFIG_ERROR);
+        }
+        print_line(&library.target);
+
+        for path in materialize::find_broken_links(Path::new(&library.target)) {
+            print_line(format_args!("Broken link {}", path.display()));
+            failure_count += 1;
+        }
+
+        // The library is read like a source folder, its episodes are already named by season
+        let scan = match Scan::new(&library.target, config, &EpisodeCounts::default(), &Overrides::default(), &resources.filter_words, &resources.file_extensions) {
+            Ok(scan) => scan,
+            Err(_) => std::process::exit(EXIT_CONFIG_ERROR),
+        };
+        for series in &scan.series {
+            for issue in series.check() {
+                print_line(&issue);
+                failure_count += 1;
+            }
+            // Extras folders hold unnumbered files on purpose
+            let unnumbered: Vec<PathBuf> = series.unresolved().into_iter()
+                .filter(|path| is_episode_folder(path.parent()))
+                .collect();
+            for path in unnumbered {
+                print_line(format_args!("Unnumbered {}", path.display()));
+                failure_count += 1;
+            }
+        }
+    }
+    failure_count
+}
+
+/// Season folders like "Season 01" and "Specials" hold numbered episodes
+fn is_episode_folder(folder: Option<&Path>) -> bool {
+    let folder_name = folder.and_then(Path::file_name).map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    folder_name.starts_with("Season ") || folder_name == "Specials"
 }
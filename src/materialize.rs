@@ -0,0 +1,297 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use log::debug;
+use log::error;
+use log::info;
+use log::warn;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// How a source file is placed in the target library
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LinkMode {
+    /// Default, keeps torrents seeding without using extra space
+    #[default]
+    Hardlink,
+    SymlinkRelative,
+    SymlinkAbsolute,
+    /// Copy on write clone, only supported on btrfs and XFS
+    Reflink,
+    Copy,
+    Move,
+}
+
+impl fmt::Display for LinkMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LinkMode::Hardlink => "hardlink",
+            LinkMode::SymlinkRelative => "symlink-relative",
+            LinkMode::SymlinkAbsolute => "symlink-absolute",
+            LinkMode::Reflink => "reflink",
+            LinkMode::Copy => "copy",
+            LinkMode::Move => "move",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for LinkMode {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.trim().to_lowercase().as_str() {
+            "hardlink" => Ok(LinkMode::Hardlink),
+            "symlink" | "symlink-relative" => Ok(LinkMode::SymlinkRelative),
+            "symlink-absolute" => Ok(LinkMode::SymlinkAbsolute),
+            "reflink" => Ok(LinkMode::Reflink),
+            "copy" => Ok(LinkMode::Copy),
+            "move" => Ok(LinkMode::Move),
+            _ => {
+                warn!("Unknown link mode {}", &input);
+                Err(())
+            }
+        }
+    }
+}
+
+impl LinkMode {
+    /// Mode to retry with when this one is not possible.
+    /// Hardlink falls back to absolute symlink so no space is wasted and seeding keeps working,
+    /// reflink falls back to plain copy.
+    fn fallback(&self) -> Option<LinkMode> {
+        match self {
+            LinkMode::Hardlink => Some(LinkMode::SymlinkAbsolute),
+            LinkMode::Reflink => Some(LinkMode::Copy),
+            _ => None,
+        }
+    }
+
+    /// Whether source and target have to live on the same file system
+    fn requires_same_device(&self) -> bool {
+        matches!(self, LinkMode::Hardlink | LinkMode::Reflink)
+    }
+
+    /// Place source file at target path, falling back to other modes when needed.
+    /// Returns the mode actually used, or None when target already exists.
+    pub fn materialize(&self, source: &Path, target: &Path) -> Result<Option<LinkMode>, ()> {
+        if target.symlink_metadata().is_ok() {
+            warn!("Target {} already exists. Skipping.", target.display());
+            return Ok(None);
+        }
+
+        let target_directory = match target.parent() {
+            Some(parent) => parent,
+            None => {
+                error!("Failed to get parent folder of {}", target.display());
+                return Err(());
+            }
+        };
+        if let Err(e) = fs::create_dir_all(target_directory) {
+            error!("Failed to create folder {}, due to {}", target_directory.display(), &e);
+            return Err(());
+        }
+
+        let mut mode = *self;
+        loop {
+            if mode.requires_same_device() && !is_same_device(source, target_directory) {
+                info!("{} and {} are on different devices, {} is not possible.", source.display(), target_directory.display(), &mode);
+            } else {
+                match mode.apply(source, target) {
+                    Ok(_) => {
+                        info!("{} {} -> {}", &mode, source.display(), target.display());
+                        return Ok(Some(mode));
+                    },
+                    Err(e) => warn!("Failed to {} {} -> {}, due to {}", &mode, source.display(), target.display(), &e),
+                }
+            }
+
+            match mode.fallback() {
+                Some(fallback) => {
+                    info!("Falling back from {} to {}", &mode, &fallback);
+                    mode = fallback;
+                },
+                None => {
+                    error!("Failed to place {} in target library.", source.display());
+                    return Err(());
+                }
+            }
+        }
+    }
+
+    fn apply(&self, source: &Path, target: &Path) -> io::Result<()> {
+        match self {
+            LinkMode::Hardlink => fs::hard_link(source, target),
+            LinkMode::SymlinkAbsolute => symlink(&fs::canonicalize(source)?, target),
+            LinkMode::SymlinkRelative => {
+                let source = fs::canonicalize(source)?;
+                let target_directory = fs::canonicalize(target.parent().unwrap_or(Path::new(".")))?;
+                symlink(&relative_path(&target_directory, &source), target)
+            },
+            LinkMode::Reflink => reflink(source, target),
+            LinkMode::Copy => fs::copy(source, target).map(|_| ()),
+            LinkMode::Move => {
+                let target_directory = target.parent().unwrap_or(Path::new("."));
+                if is_same_device(source, target_directory) {
+                    fs::rename(source, target)
+                } else {
+                    // Rename does not work across devices
+                    debug!("Moving {} across devices by copy and remove.", source.display());
+                    fs::copy(source, target)?;
+                    fs::remove_file(source)
+                }
+            },
+        }
+    }
+}
+
+/// Check whether two paths are on the same device
+#[cfg(unix)]
+fn is_same_device(source: &Path, target: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(source), fs::metadata(target)) {
+        (Ok(source), Ok(target)) => source.dev() == target.dev(),
+        _ => false,
+    }
+}
+
+/// Check whether two paths are on the same device
+#[cfg(not(unix))]
+fn is_same_device(_source: &Path, _target: &Path) -> bool {
+    // Let the link attempt decide
+    true
+}
+
+#[cfg(unix)]
+fn symlink(source: &Path, target: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(source, target)
+}
+
+#[cfg(windows)]
+fn symlink(source: &Path, target: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(source, target)
+}
+
+/// Clone file with FICLONE ioctl
+#[cfg(target_os = "linux")]
+fn reflink(source: &Path, target: &Path) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    const FICLONE: libc::c_ulong = 0x40049409;
+
+    let source_file = fs::File::open(source)?;
+    let target_file = fs::OpenOptions::new().write(true).create_new(true).open(target)?;
+    let result = unsafe { libc::ioctl(target_file.as_raw_fd(), FICLONE as _, source_file.as_raw_fd()) };
+    if result == -1 {
+        let e = io::Error::last_os_error();
+        drop(target_file);
+        fs::remove_file(target)?;
+        return Err(e);
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reflink(_source: &Path, _target: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "reflink is only supported on Linux"))
+}
+
+/// Path to reach `to` from inside `from_directory`, both have to be absolute
+fn relative_path(from_directory: &Path, to: &Path) -> PathBuf {
+    let from: Vec<Component> = from_directory.components().collect();
+    let to: Vec<Component> = to.components().collect();
+    let common = from.iter().zip(to.iter()).take_while(|(a, b)| a == b).count();
+
+    let mut result = PathBuf::new();
+    for _ in common..from.len() {
+        result.push("..");
+    }
+    for component in &to[common..] {
+        result.push(component);
+    }
+    result
+}
+
+/// Symbolic links in folder and its sub folders whose source is gone
+pub fn find_broken_links(folder_path: &Path) -> Vec<PathBuf> {
+    let mut result = Vec::new();
+    let mut pending = vec![folder_path.to_path_buf()];
+    while let Some(path) = pending.pop() {
+        let paths = match fs::read_dir(&path) {
+            Ok(paths) => paths,
+            Err(e) => {
+                warn!("Failed open {}, due to {}", path.display(), &e);
+                continue;
+            }
+        };
+        for entry in paths.flatten() {
+            let path = entry.path();
+            match (entry.file_type(), fs::metadata(&path)) {
+                (Ok(file_type), Err(_)) if file_type.is_symlink() => result.push(path),
+                (Ok(file_type), _) if file_type.is_dir() => pending.push(path),
+                _ => (),
+            }
+        }
+    }
+    result.sort();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use log::info;
+    use super::LinkMode;
+
+    fn setup() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn link_mode_materialization() {
+        // Setup
+        setup();
+        let test_directory = std::env::temp_dir().join(format!("anime-organizer-rs-materialization-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&test_directory);
+        fs::create_dir_all(&test_directory).unwrap();
+
+        // Run test
+        for mode in [LinkMode::Hardlink, LinkMode::SymlinkRelative, LinkMode::SymlinkAbsolute, LinkMode::Reflink, LinkMode::Copy, LinkMode::Move] {
+            let source = test_directory.join(format!("source/{}.mkv", &mode));
+            let target = test_directory.join(format!("target/Series/Season 01/{}.mkv", &mode));
+            fs::create_dir_all(source.parent().unwrap()).unwrap();
+            fs::write(&source, mode.to_string()).unwrap();
+
+            let used_mode = mode.materialize(&source, &target).unwrap().unwrap();
+            info!("{}: {}", &mode, &used_mode);
+            assert_eq!(fs::read_to_string(&target).unwrap(), mode.to_string());
+            assert_eq!(Path::new(&source).exists(), mode != LinkMode::Move);
+
+            // Existing target is skipped
+            if mode != LinkMode::Move {
+                assert_eq!(mode.materialize(&source, &target).unwrap(), None);
+            }
+        }
+
+        // A file where the target folder should be fails the entry
+        let source = test_directory.join("source/blocked.mkv");
+        fs::write(&source, "blocked").unwrap();
+        fs::write(test_directory.join("target/Blocked"), "file").unwrap();
+        assert!(LinkMode::Copy.materialize(&source, &test_directory.join("target/Blocked/Season 01/blocked.mkv")).is_err());
+
+        let _ = fs::remove_dir_all(&test_directory);
+    }
+
+    #[test]
+    fn relative_path() {
+        use super::relative_path;
+        assert_eq!(relative_path(Path::new("/a/b/c"), Path::new("/a/d/e.mkv")), Path::new("../../d/e.mkv"));
+        assert_eq!(relative_path(Path::new("/a"), Path::new("/a/e.mkv")), Path::new("e.mkv"));
+    }
+}
@@ -0,0 +1,375 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use log::debug;
+use log::error;
+use log::warn;
+use regex::Regex;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::series::EpisodeType;
+
+/// Media server whose naming conventions the target library follows
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NamingProfile {
+    #[default]
+    Jellyfin,
+    Plex,
+    Kodi,
+    Emby,
+}
+
+impl fmt::Display for NamingProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            NamingProfile::Jellyfin => "jellyfin",
+            NamingProfile::Plex => "plex",
+            NamingProfile::Kodi => "kodi",
+            NamingProfile::Emby => "emby",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for NamingProfile {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.trim().to_lowercase().as_str() {
+            "jellyfin" => Ok(NamingProfile::Jellyfin),
+            "plex" => Ok(NamingProfile::Plex),
+            "kodi" => Ok(NamingProfile::Kodi),
+            "emby" => Ok(NamingProfile::Emby),
+            _ => {
+                warn!("Unknown naming profile {}", &input);
+                Err(())
+            }
+        }
+    }
+}
+
+/// How main episodes are numbered in the target library
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Numbering {
+    /// Default, episodes restart in every season
+    #[default]
+    Seasonal,
+    /// Every main episode in season 1 with its absolute number, for libraries in absolute order
+    Absolute,
+}
+
+impl fmt::Display for Numbering {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Numbering::Seasonal => "seasonal",
+            Numbering::Absolute => "absolute",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Numbering {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.trim().to_lowercase().as_str() {
+            "seasonal" => Ok(Numbering::Seasonal),
+            "absolute" => Ok(Numbering::Absolute),
+            _ => {
+                warn!("Unknown numbering {}", &input);
+                Err(())
+            }
+        }
+    }
+}
+
+/// Placeholders available in path templates.
+/// Episode titles are not parsed from file names, so title always vanishes.
+pub const TEMPLATE_FIELDS: [&str; 14] = ["series", "year", "season", "episode", "last", "absolute", "type", "title", "group", "resolution", "codec", "crc", "lang", "ext"];
+
+/// How resolved series are named in the target library
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Naming {
+    pub profile: NamingProfile,
+    /// Subtitle language flagged as default, for servers supporting it
+    pub default_subtitle_language: Option<String>,
+    /// Path template for main episodes and specials, overriding the profile
+    pub template: Option<String>,
+    pub numbering: Numbering,
+}
+
+/// Everything known about a file when naming it
+#[derive(Debug, Default, Clone)]
+pub struct NamingFields {
+    pub series: String,
+    pub year: Option<u16>,
+    pub season: u16,
+    pub episode_type: EpisodeType,
+    pub episode: u16,
+    /// Last episode of a multi-episode file
+    pub last_episode: Option<u16>,
+    /// Episode number counted from the first episode of the show, when episode counts of earlier seasons are known
+    pub absolute: Option<u16>,
+    pub group: Option<String>,
+    pub resolution: Option<String>,
+    pub codec: Option<String>,
+    pub crc: Option<String>,
+    /// Subtitle language with flags, or attachment suffix, like "zh-Hans.default"
+    pub lang: Option<String>,
+    pub ext: String,
+    /// File name without extension in the source folder
+    pub original_name: String,
+}
+
+impl NamingFields {
+    fn to_map(&self) -> HashMap<&'static str, String> {
+        let mut result = HashMap::new();
+        result.insert("series", self.series.clone());
+        result.insert("season", self.season.to_string());
+        result.insert("episode", self.episode.to_string());
+        result.insert("type", format!("{:?}", &self.episode_type).to_lowercase());
+        result.insert("ext", self.ext.clone());
+        let optional_fields = [
+            ("year", self.year.map(|year| year.to_string())),
+            ("last", self.last_episode.map(|last_episode| last_episode.to_string())),
+            ("absolute", self.absolute.map(|absolute| absolute.to_string())),
+            ("group", self.group.clone()),
+            ("resolution", self.resolution.clone()),
+            ("codec", self.codec.clone()),
+            ("crc", self.crc.clone()),
+            ("lang", self.lang.clone()),
+        ];
+        for (key, value) in optional_fields {
+            if let Some(value) = value.filter(|value| !value.is_empty()) {
+                result.insert(key, value);
+            }
+        }
+        result
+    }
+}
+
+/// Check a path template for unknown placeholders
+pub fn check_template(template: &str) -> Result<(), ()> {
+    let mut result = Ok(());
+    for caps in Regex::new(r"\{([^}]*)\}").unwrap().captures_iter(template) {
+        match parse_placeholder(&caps[1]) {
+            Some((_, field, _, _)) if TEMPLATE_FIELDS.contains(&field) => (),
+            _ => {
+                error!("Unknown placeholder {{{}}} in template, expecting one of {}", &caps[1], TEMPLATE_FIELDS.join(", "));
+                result = Err(());
+            }
+        }
+    }
+    result
+}
+
+/// Whether a template has a placeholder of the field
+fn template_has_field(template: &str, field: &str) -> bool {
+    Regex::new(r"\{([^}]*)\}").unwrap().captures_iter(template)
+        .any(|caps| parse_placeholder(&caps[1]).is_some_and(|(_, placeholder_field, _, _)| placeholder_field == field))
+}
+
+/// Split a placeholder like " (year)" or "season:02" into prefix, field, width and suffix
+fn parse_placeholder(placeholder: &str) -> Option<(&str, &str, usize, &str)> {
+    let caps = Regex::new(r"^([^a-z]*?)([a-z]+)(?::(\d+))?([^a-z]*)$").unwrap().captures(placeholder)?;
+    let width = caps.get(3).map(|width| width.as_str().parse().unwrap_or(0)).unwrap_or(0);
+    Some((
+        caps.get(1).map_or("", |i| i.as_str()),
+        caps.get(2).map_or("", |i| i.as_str()),
+        width,
+        caps.get(4).map_or("", |i| i.as_str()),
+    ))
+}
+
+/// Fill a template, placeholders of unknown fields vanish together with their literal text
+pub fn render_template(template: &str, fields: &NamingFields) -> String {
+    // Test covered
+    let fields = fields.to_map();
+    let result = Regex::new(r"\{([^}]*)\}").unwrap().replace_all(template, |caps: &regex::Captures| {
+        let (prefix, field, width, suffix) = match parse_placeholder(&caps[1]) {
+            Some(placeholder) => placeholder,
+            None => return String::new(),
+        };
+        match fields.get(field) {
+            Some(value) => format!("{}{:0>width$}{}", prefix, value, suffix, width = width),
+            None => String::new(),
+        }
+    });
+    debug!("Rendered template {} into {}", &template, &result);
+    result.to_string()
+}
+
+impl Naming {
+    /// Folder of the series in target directory, year tells remakes apart like "Fruits Basket (2019)"
+    pub fn series_folder(&self, series_name: &str, year: Option<u16>) -> String {
+        match year {
+            Some(year) => format!("{} ({})", series_name, year),
+            None => series_name.to_string(),
+        }
+    }
+
+    /// Folder inside the series folder for a kind of episode
+    pub fn episode_folder(&self, season: u16, episode_type: EpisodeType) -> PathBuf {
+        let folder = match (self.profile, episode_type) {
+            (_, EpisodeType::Main) => format!("Season {:02}", season),
+            (NamingProfile::Jellyfin, EpisodeType::Special) => "Season 00".to_string(),
+            (_, EpisodeType::Special) => "Specials".to_string(),
+            // Kodi only picks up a single extras folder
+            (NamingProfile::Kodi, _) => "Extras".to_string(),
+            (_, EpisodeType::Trailer) => "Trailers".to_string(),
+            (NamingProfile::Emby, _) => "Extras".to_string(),
+            (_, EpisodeType::Opening | EpisodeType::Ending) => "Featurettes".to_string(),
+            (NamingProfile::Plex, _) => "Other".to_string(),
+            (NamingProfile::Jellyfin, _) => "Extras".to_string(),
+        };
+        PathBuf::from(folder)
+    }
+
+    /// File name without extension of a main episode or special, extras keep their original name.
+    /// Multi-episode files end with the last episode, like S01E01-E02.
+    pub fn episode_name(&self, series_name: &str, season: u16, episode_type: EpisodeType, episode: u16, last_episode: Option<u16>, original_name: &str) -> String {
+        let last_episode = match last_episode {
+            Some(last_episode) => format!("-E{:02}", last_episode),
+            None => String::new(),
+        };
+        match episode_type {
+            EpisodeType::Main => format!("{} - S{:02}E{:02}{}", series_name, season, episode, last_episode),
+            EpisodeType::Special => format!("{} - S00E{:02}{}", series_name, episode, last_episode),
+            _ => original_name.to_string(),
+        }
+    }
+
+    /// Subtitle name part between episode name and extension, like ".zh-Hans.default"
+    pub fn subtitle_suffix(&self, language: &str, default: bool, forced: bool) -> String {
+        let mut result = String::new();
+        if !language.is_empty() {
+            result.push('.');
+            result.push_str(language);
+        }
+
+        // Plex and Kodi only understand the forced flag
+        if default && matches!(self.profile, NamingProfile::Jellyfin | NamingProfile::Emby) {
+            result.push_str(".default");
+        }
+        if forced {
+            result.push_str(".forced");
+        }
+        result
+    }
+
+    /// Path of a file relative to target directory
+    pub fn target_path(&self, fields: &NamingFields) -> PathBuf {
+        let fields = &match (self.numbering, fields.episode_type, fields.absolute) {
+            (Numbering::Absolute, EpisodeType::Main, Some(absolute)) => NamingFields {
+                season: 1,
+                episode: absolute,
+                last_episode: fields.last_episode.map(|last_episode| last_episode + absolute - fields.episode),
+                ..fields.clone()
+            },
+            _ => fields.clone(),
+        };
+
+        if let (Some(template), EpisodeType::Main | EpisodeType::Special) = (&self.template, fields.episode_type) {
+            let path = render_template(template, fields);
+            // Subtitles of one episode would share a path without their language
+            return match fields.lang.as_ref().filter(|lang| !lang.is_empty() && !template_has_field(template, "lang")) {
+                Some(lang) => match path.strip_suffix(&format!(".{}", &fields.ext)) {
+                    Some(stem) => PathBuf::from(format!("{}.{}.{}", stem, lang, &fields.ext)),
+                    None => PathBuf::from(format!("{}.{}", path, lang)),
+                },
+                None => PathBuf::from(path),
+            };
+        }
+
+        let episode_name = self.episode_name(&fields.series, fields.season, fields.episode_type, fields.episode, fields.last_episode, &fields.original_name);
+        let file_name = match &fields.lang {
+            Some(lang) if !lang.is_empty() => format!("{}.{}.{}", &episode_name, lang, &fields.ext),
+            _ => format!("{}.{}", &episode_name, &fields.ext),
+        };
+        PathBuf::from(self.series_folder(&fields.series, fields.year))
+            .join(self.episode_folder(fields.season, fields.episode_type))
+            .join(file_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::load_env_var;
+    use log::info;
+    use serde::Deserialize;
+    use crate::series::EpisodeType;
+    use super::{check_template, render_template, Naming, NamingFields};
+
+    #[derive(Deserialize)]
+    struct NamingTemplate {
+        template: String,
+        series: String,
+        year: Option<u16>,
+        season: u16,
+        episode: u16,
+        #[serde(default)]
+        last_episode: Option<u16>,
+        group: Option<String>,
+        resolution: Option<String>,
+        crc: Option<String>,
+        lang: Option<String>,
+        ext: String,
+        result: String,
+    }
+
+    #[test]
+    fn naming_template() {
+        // Setup
+        dotenvy::from_filename("test.env").unwrap();
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        // Load test sheet
+        let test_sheet: Vec<NamingTemplate> = serde_json::from_str(&fs::read_to_string(load_env_var("TEST_NAMING_TEMPLATE").unwrap()).unwrap()).expect("JSON was not well-formatted");
+
+        // Run test
+        for i in test_sheet {
+            let fields = NamingFields {
+                series: i.series,
+                year: i.year,
+                season: i.season,
+                episode_type: EpisodeType::Main,
+                episode: i.episode,
+                last_episode: i.last_episode,
+                group: i.group,
+                resolution: i.resolution,
+                crc: i.crc,
+                lang: i.lang,
+                ext: i.ext,
+                ..Default::default()
+            };
+            info!("{}: {}", &i.template, &i.result);
+            assert!(check_template(&i.template).is_ok());
+            assert_eq!(render_template(&i.template, &fields), i.result);
+        }
+
+        assert!(check_template("{series}/{seasn:02}").is_err());
+        assert!(check_template("{series} - S{season:02}E{episode:02}{ - title}").is_ok());
+
+        // Subtitle languages are kept when the template has no {.lang}
+        let naming = Naming {
+            template: Some("{series}/{series} {episode:03}.{ext}".to_string()),
+            ..Default::default()
+        };
+        let fields = NamingFields {
+            series: "One Piece".to_string(),
+            episode: 1,
+            lang: Some("zh-Hant".to_string()),
+            ext: "ass".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(naming.target_path(&fields), PathBuf::from("One Piece/One Piece 001.zh-Hant.ass"));
+    }
+}
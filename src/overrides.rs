@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::marker::PhantomData;
+use std::path::Path;
+use log::debug;
+use log::error;
+use log::info;
+use regex::Regex;
+use serde::de::MapAccess;
+use serde::de::Visitor;
+use serde::Deserialize;
+use serde::Deserializer;
+
+use crate::series::EpisodeType;
+
+/// Sidecar file in a source folder, pinning what the heuristics get wrong
+pub const OVERRIDE_FILE_NAME: &str = ".anime-organizer.yaml";
+
+/// Answers forced for one source folder, every field is optional
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SeriesOverride {
+    pub name: Option<String>,
+    pub year: Option<u16>,
+    /// Season of every file in the folder
+    pub season: Option<u16>,
+    /// Added to every main episode number, negative for seasons numbered on from the previous one
+    pub episode_offset: Option<i32>,
+    /// Regex whose first capture group is the episode number, tried on file names before the heuristics
+    pub episode_regex: Option<String>,
+    /// Glob patterns of files to leave out, relative to the folder
+    pub ignore: Vec<String>,
+    /// Episode type by glob pattern of file names, in the order of the file
+    #[serde(deserialize_with = "ordered_map")]
+    pub types: Vec<(String, EpisodeType)>,
+}
+
+/// Overrides by source folder name, kept in one central file
+#[derive(Debug, Default, Deserialize)]
+pub struct Overrides(HashMap<String, SeriesOverride>);
+
+/// Read a mapping into its entries, keeping the order they are written in
+fn ordered_map<'de, D, K, V>(deserializer: D) -> Result<Vec<(K, V)>, D::Error>
+where
+    D: Deserializer<'de>,
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+{
+    struct OrderedMap<K, V>(PhantomData<(K, V)>);
+
+    impl<'de, K: Deserialize<'de>, V: Deserialize<'de>> Visitor<'de> for OrderedMap<K, V> {
+        type Value = Vec<(K, V)>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a map")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut result = Vec::new();
+            while let Some(entry) = map.next_entry()? {
+                result.push(entry);
+            }
+            Ok(result)
+        }
+    }
+
+    deserializer.deserialize_map(OrderedMap(PhantomData))
+}
+
+/// Check a name against a glob pattern with * and ?
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    glob_to_regex(pattern).is_match(name)
+}
+
+/// Convert a glob pattern with * and ? into an anchored regex
+fn glob_to_regex(pattern: &str) -> Regex {
+    let mut result = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => result.push_str(".*"),
+            '?' => result.push('.'),
+            _ => result.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    result.push('$');
+    Regex::new(&result).unwrap()
+}
+
+fn parse(content: &str, file_path: &str) -> Result<SeriesOverride, ()> {
+    let series_override = match serde_yaml::from_str::<SeriesOverride>(content) {
+        Ok(series_override) => series_override,
+        Err(e) => {
+            error!("Error parsing YAML {}: {}", &file_path, e);
+            return Err(());
+        }
+    };
+    series_override.check(file_path)?;
+    Ok(series_override)
+}
+
+impl SeriesOverride {
+    /// Check the episode regex compiles and captures a number
+    fn check(&self, file_path: &str) -> Result<(), ()> {
+        if let Some(episode_regex) = &self.episode_regex {
+            match Regex::new(episode_regex) {
+                Ok(reg) if reg.captures_len() > 1 => (),
+                Ok(_) => {
+                    error!("episode_regex {} in {} has no capture group for the episode number", &episode_regex, &file_path);
+                    return Err(());
+                },
+                Err(e) => {
+                    error!("Invalid episode_regex {} in {}, due to {}", &episode_regex, &file_path, e);
+                    return Err(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_ignored(&self, file_name: &str) -> bool {
+        self.ignore.iter().any(|pattern| glob_match(pattern, file_name))
+    }
+
+    /// Episode type forced by the first matching pattern
+    pub fn episode_type(&self, file_name: &str) -> Option<EpisodeType> {
+        self.types.iter()
+            .find(|(pattern, _)| glob_match(pattern, file_name))
+            .map(|(_, episode_type)| *episode_type)
+    }
+
+    /// Episode number captured by the episode regex
+    pub fn episode_number(&self, file_name: &str) -> Option<u16> {
+        let caps = Regex::new(self.episode_regex.as_ref()?).ok()?.captures(file_name)?;
+        caps.get(1)?.as_str().parse().ok()
+    }
+
+    /// Shift a main episode number by the episode offset, None when it would drop below zero
+    pub fn offset_episode(&self, episode: u16) -> Option<u16> {
+        u16::try_from(episode as i32 + self.episode_offset.unwrap_or_default()).ok()
+    }
+}
+
+impl Overrides {
+    pub fn load(file_path: &str) -> Result<Overrides, ()> {
+        let overrides_string = match fs::read_to_string(file_path) {
+            Ok(content) => content,
+            Err(e) => {
+                error!("Failed to load overrides, please check {}, due to {}", &file_path, &e);
+                return Err(());
+            }
+        };
+
+        match serde_yaml::from_str::<Overrides>(&overrides_string) {
+            Ok(content) => {
+                for (folder_name, series_override) in &content.0 {
+                    series_override.check(&format!("{} ({})", &file_path, &folder_name))?;
+                }
+                info!("Load overrides of {} folders successfully.", content.0.len());
+                Ok(content)
+            },
+            Err(err) => {
+                error!("Error parsing YAML {}: {}", &file_path, err);
+                Err(())
+            }
+        }
+    }
+
+    /// Overrides of a source folder, its sidecar file wins over the central file
+    pub fn get(&self, folder_path: &Path) -> Result<SeriesOverride, ()> {
+        let sidecar_path = folder_path.join(OVERRIDE_FILE_NAME);
+        if let Ok(content) = fs::read_to_string(&sidecar_path) {
+            info!("Using overrides in {}", sidecar_path.display());
+            return parse(&content, &sidecar_path.to_string_lossy());
+        }
+
+        let folder_name = folder_path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        match self.0.get(&folder_name) {
+            Some(series_override) => {
+                info!("Using central overrides of {}", &folder_name);
+                Ok(series_override.clone())
+            },
+            None => {
+                debug!("No overrides for {}", &folder_name);
+                Ok(SeriesOverride::default())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::config::Config;
+    use crate::plan::Plan;
+    use crate::series::{EpisodeCounts, FileExtensions, FilterWords};
+    use super::{Overrides, OVERRIDE_FILE_NAME};
+
+    #[test]
+    fn series_override() {
+        // Setup
+        let _ = env_logger::builder().is_test(true).try_init();
+        let test_directory = std::env::temp_dir().join(format!("anime-organizer-rs-overrides-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&test_directory);
+        let source_directory = test_directory.join("source");
+        let target_directory = test_directory.join("target");
+
+        // Second season released with numbers carried on from the first one, under a romanized name
+        let folder = source_directory.join("[Group] Kimetsu no Yaiba - Yuukaku-hen");
+        let file_names = [
+            "Kimetsu no Yaiba - Yuukaku-hen - 27 [1080p].mkv",
+            "Kimetsu no Yaiba - Yuukaku-hen - 28 [1080p].mkv",
+            "Kimetsu no Yaiba - Yuukaku-hen - Recap 01 [1080p].mkv",
+            "sample.mkv",
+        ];
+        fs::create_dir_all(&folder).unwrap();
+        for file_name in file_names {
+            fs::write(folder.join(file_name), file_name).unwrap();
+        }
+        // Types are tried in file order, the recap stays a special although *01* sorts first
+        fs::write(folder.join(OVERRIDE_FILE_NAME), [
+            "name: Demon Slayer",
+            "year: 2019",
+            "season: 3",
+            "episode_offset: -26",
+            "ignore: [sample.mkv]",
+            "types:",
+            "  '*Recap*': special",
+            "  '*01*': main",
+        ].join("\n")).unwrap();
+
+        // Run test
+        let filter_words = FilterWords::load();
+        let file_extensions = FileExtensions::load();
+        let plan = Plan::build(&source_directory.to_string_lossy(), &target_directory.to_string_lossy(), &Config::default(), &EpisodeCounts::default(), &Overrides::default(), &filter_words, &file_extensions).unwrap();
+        let mut target_paths: Vec<String> = plan.entries.iter()
+            .map(|entry| entry.target.strip_prefix(&target_directory).unwrap().to_string_lossy().to_string())
+            .collect();
+        target_paths.sort();
+        assert_eq!(target_paths, [
+            "Demon Slayer (2019)/Season 00/Demon Slayer - S00E01.mkv",
+            "Demon Slayer (2019)/Season 03/Demon Slayer - S03E01.mkv",
+            "Demon Slayer (2019)/Season 03/Demon Slayer - S03E02.mkv",
+        ]);
+        let entry = plan.entries.iter().find(|entry| entry.target.ends_with("Demon Slayer - S03E01.mkv")).unwrap();
+        assert_eq!(entry.overridden, ["name", "year", "season", "episode"]);
+        assert!(plan.unresolved.is_empty());
+
+        // A broken sidecar leaves the folder unresolved
+        fs::write(folder.join(OVERRIDE_FILE_NAME), "episode_regex: '[0-9]+'").unwrap();
+        let plan = Plan::build(&source_directory.to_string_lossy(), &target_directory.to_string_lossy(), &Config::default(), &EpisodeCounts::default(), &Overrides::default(), &filter_words, &file_extensions).unwrap();
+        assert!(plan.entries.is_empty());
+        assert_eq!(plan.unresolved, [folder]);
+
+        let _ = fs::remove_dir_all(&test_directory);
+    }
+}
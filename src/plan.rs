@@ -0,0 +1,387 @@
+use std::cell::LazyCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::io::Write;
+use std::path::PathBuf;
+use log::debug;
+use log::error;
+use log::info;
+use log::warn;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::journal::Journal;
+use crate::materialize::LinkMode;
+use crate::overrides::glob_match;
+use crate::overrides::Overrides;
+use crate::series::EpisodeCandidate;
+use crate::series::EpisodeCounts;
+use crate::series::EpisodeNumberRule;
+use crate::series::FileExtensions;
+use crate::series::FilterWords;
+use crate::series::SeasonIssue;
+use crate::series::Series;
+
+/// Entries below this confidence are flagged for manual review
+pub const REVIEW_CONFIDENCE: f32 = 0.5;
+
+/// Everything the organizer would do in one run, without touching the disk
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Plan {
+    pub source_directory: PathBuf,
+    pub target_directory: PathBuf,
+    pub entries: Vec<PlanEntry>,
+    /// Source folders which could not be resolved into a series, files without episode number, files of episodes merged from another folder already and files sharing a target with another
+    pub unresolved: Vec<PathBuf>,
+    /// Missing, duplicate and outlier episodes found in resolved seasons
+    #[serde(default)]
+    pub issues: Vec<SeasonIssue>,
+}
+
+/// A single file placement in the target library
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanEntry {
+    pub series: String,
+    pub source: PathBuf,
+    pub target: PathBuf,
+    pub action: LinkMode,
+    pub confidence: f32,
+    pub reason: String,
+    /// Rule which picked the episode number
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<EpisodeNumberRule>,
+    /// Every episode number found in the source name, ranked by score
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub candidates: Vec<EpisodeCandidate>,
+    /// Low confidence guess, worth checking before applying
+    #[serde(default)]
+    pub review: bool,
+    /// Fields forced by overrides, like "name" or "episode"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overridden: Vec<String>,
+}
+
+/// Find an already resolved folder of the same show, like "Show" for "Show S2".
+/// Names have to match and years must not differ, a folder without year only joins an unambiguous show.
+fn find_same_series(series_list: &[Series], series: &Series) -> Option<usize> {
+    let candidates: Vec<usize> = series_list.iter().enumerate()
+        .filter(|(_, i)| i.name().to_lowercase() == series.name().to_lowercase())
+        .map(|(index, _)| index)
+        .collect();
+
+    if let Some(index) = candidates.iter().find(|index| series_list[**index].year() == series.year()) {
+        return Some(*index);
+    }
+    match candidates.as_slice() {
+        [] => None,
+        [index] if series_list[*index].year().is_none() || series.year().is_none() => Some(*index),
+        // Remake from another year
+        [_] => None,
+        _ => {
+            warn!("{} matches several shows of different years, keeping it apart.", series.title());
+            None
+        }
+    }
+}
+
+/// Keep one entry per target, the most confident one, sources of the others are left unresolved
+fn remove_duplicate_targets(entries: Vec<PlanEntry>, unresolved: &mut Vec<PathBuf>) -> Vec<PlanEntry> {
+    let mut result: Vec<PlanEntry> = Vec::new();
+    let mut indexes = HashMap::<PathBuf, usize>::new();
+    for entry in entries {
+        let index = match indexes.get(&entry.target) {
+            Some(index) => *index,
+            None => {
+                indexes.insert(entry.target.clone(), result.len());
+                result.push(entry);
+                continue;
+            }
+        };
+        let duplicate = match entry.confidence > result[index].confidence {
+            true => std::mem::replace(&mut result[index], entry),
+            false => entry,
+        };
+        warn!("{} would take the place of {} at {}, leaving it unresolved", duplicate.source.display(), result[index].source.display(), duplicate.target.display());
+        unresolved.push(duplicate.source);
+    }
+    result
+}
+
+/// Series found in a source directory, before they are named in the target library
+pub struct Scan {
+    pub series: Vec<Series>,
+    /// Source folders which could not be resolved into a series
+    pub unresolved: Vec<PathBuf>,
+}
+
+impl Scan {
+    /// Resolve every series folder in source directory, merging folders of the same show
+    pub fn new(source_directory: &str, config: &Config, episode_counts: &EpisodeCounts, overrides: &Overrides, filter_words: &FilterWords, file_extensions: &FileExtensions) -> Result<Scan, ()> {
+        let paths = match fs::read_dir(source_directory) {
+            Ok(paths) => paths,
+            Err(e) => {
+                error!("Failed open {}, due to {}", &source_directory, &e);
+                return Err(());
+            }
+        };
+
+        let mut scan = Scan {
+            series: Vec::new(),
+            unresolved: Vec::new(),
+        };
+
+        let mut paths: Vec<PathBuf> = paths.flatten().map(|entry| entry.path()).collect();
+        paths.sort();
+        for path in paths {
+            if !path.is_dir() {
+                debug!("Skipping {}, not a folder.", path.display());
+                continue;
+            }
+            let folder_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+            if config.ignore.iter().any(|pattern| glob_match(pattern, &folder_name)) {
+                info!("Ignoring {}, as configured", path.display());
+                continue;
+            }
+
+            match Series::new(&path.to_string_lossy(), config.split_cour, overrides, &config.ignore, filter_words, file_extensions) {
+                Ok(series) => match find_same_series(&scan.series, &series) {
+                    Some(index) => scan.series[index].merge(series),
+                    None => scan.series.push(series),
+                },
+                Err(_) => {
+                    warn!("Failed to resolve series in {}", path.display());
+                    scan.unresolved.push(path);
+                }
+            };
+        }
+
+        for series in &mut scan.series {
+            series.renumber(episode_counts);
+        }
+
+        Ok(scan)
+    }
+}
+
+impl Plan {
+    /// Resolve every series folder in source directory
+    pub fn build(source_directory: &str, target_directory: &str, config: &Config, episode_counts: &EpisodeCounts, overrides: &Overrides, filter_words: &FilterWords, file_extensions: &FileExtensions) -> Result<Plan, ()> {
+        let scan = Scan::new(source_directory, config, episode_counts, overrides, filter_words, file_extensions)?;
+        let mut plan = Plan {
+            source_directory: PathBuf::from(source_directory),
+            target_directory: PathBuf::from(target_directory),
+            entries: Vec::new(),
+            unresolved: scan.unresolved,
+            issues: Vec::new(),
+        };
+
+        for series in scan.series {
+            info!("Planned {}", series.title());
+            plan.unresolved.append(&mut series.unresolved());
+            for issue in series.check() {
+                warn!("{}", &issue);
+                plan.issues.push(issue);
+            }
+            plan.entries.append(&mut series.plan(target_directory, config.link_mode, &config.naming));
+        }
+        plan.entries = remove_duplicate_targets(plan.entries, &mut plan.unresolved);
+
+        for entry in plan.entries.iter().filter(|entry| entry.review) {
+            warn!("Review {}, confidence {:.2}: {}", entry.source.display(), &entry.confidence, &entry.reason);
+        }
+
+        Ok(plan)
+    }
+
+    /// Write plan as JSON to given file, or stdout when no file is given
+    pub fn save(&self, file_path: Option<&str>) -> Result<(), ()> {
+        let content = match serde_json::to_string_pretty(self) {
+            Ok(content) => content,
+            Err(e) => {
+                error!("Failed to serialize plan, due to {}", &e);
+                return Err(());
+            }
+        };
+
+        match file_path {
+            Some(file_path) => match fs::write(file_path, content) {
+                Ok(_) => {
+                    info!("Saved plan to {}", &file_path);
+                    Ok(())
+                },
+                Err(e) => {
+                    error!("Failed to write plan to {}, due to {}", &file_path, &e);
+                    Err(())
+                }
+            },
+            None => match writeln!(std::io::stdout().lock(), "{}", content) {
+                Ok(_) => Ok(()),
+                // Reader closed early, like `plan | head`
+                Err(e) if e.kind() == ErrorKind::BrokenPipe => {
+                    debug!("Output closed before the whole plan was written");
+                    Ok(())
+                },
+                Err(e) => {
+                    error!("Failed to write plan, due to {}", &e);
+                    Err(())
+                }
+            }
+        }
+    }
+
+    pub fn load(file_path: &str) -> Result<Plan, ()> {
+        let content = match fs::read_to_string(file_path) {
+            Ok(content) => content,
+            Err(e) => {
+                error!("Failed to read plan {}, due to {}", &file_path, &e);
+                return Err(());
+            }
+        };
+
+        match serde_json::from_str(&content) {
+            Ok(plan) => {
+                info!("Loaded plan from {}", &file_path);
+                Ok(plan)
+            },
+            Err(e) => {
+                error!("Failed to parse plan {}, due to {}", &file_path, &e);
+                Err(())
+            }
+        }
+    }
+
+    /// Execute every entry of the plan and journal it, returns the number of failed entries.
+    /// An existing target fails the entry, unless an earlier run placed the same source there.
+    pub fn apply(&self, journal: &Journal) -> usize {
+        let mut failure_count = 0;
+        let placed = LazyCell::new(|| journal.placed());
+        for entry in &self.entries {
+            match entry.action.materialize(&entry.source, &entry.target) {
+                Ok(Some(action)) => {
+                    if journal.record(entry, action).is_err() {
+                        failure_count += 1;
+                    }
+                },
+                Ok(None) if placed.contains(&(entry.source.clone(), entry.target.clone())) => debug!("{} is already placed at {}", entry.source.display(), entry.target.display()),
+                Ok(None) => {
+                    error!("Failed to place {}, {} is taken by another file", entry.source.display(), entry.target.display());
+                    failure_count += 1;
+                },
+                Err(_) => failure_count += 1,
+            }
+        }
+        failure_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::config::Config;
+    use crate::journal::{Journal, JOURNAL_FILE_NAME};
+    use crate::overrides::Overrides;
+    use crate::series::{EpisodeCounts, FileExtensions, FilterWords};
+    use super::Plan;
+
+    #[test]
+    fn plan_series_merge() {
+        // Setup
+        let _ = env_logger::builder().is_test(true).try_init();
+        let test_directory = std::env::temp_dir().join(format!("anime-organizer-rs-plan-merge-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&test_directory);
+        let source_directory = test_directory.join("source");
+        let target_directory = test_directory.join("target");
+
+        // Seasons, duplicate releases, specials and remakes downloaded separately
+        let file_names = [
+            "[Airota] Yuru Camp [01-02][1080p]/[Airota] Yuru Camp [01][1080p].mkv",
+            "[Airota] Yuru Camp [01-02][1080p]/[Airota] Yuru Camp [02][1080p].mkv",
+            "[Kamigami] Yuru Camp [01-12][1080p]/[Kamigami] Yuru Camp [01][1080p].mkv",
+            "[VCB-Studio] Yuru Camp S2 [Ma10p_1080p]/[VCB-Studio] Yuru Camp S2 [01][Ma10p_1080p].mkv",
+            "[VCB-Studio] Yuru Camp S2 [Ma10p_1080p]/[VCB-Studio] Yuru Camp S2 [02][Ma10p_1080p].mkv",
+            "Yuru Camp OVA/Yuru Camp - 01.mkv",
+            "[VCB-Studio] Fruits Basket (2019) [Ma10p_1080p]/[VCB-Studio] Fruits Basket [01][Ma10p_1080p].mkv",
+            "Fruits Basket [2001][DVDRip]/Fruits Basket [01][DVDRip].mkv",
+        ];
+        for file_name in file_names {
+            let path = source_directory.join(file_name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, file_name).unwrap();
+        }
+
+        // Linked folder looping back into the source directory
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&source_directory, source_directory.join("Yuru Camp OVA/loop")).unwrap();
+
+        // Run test
+        let plan = Plan::build(&source_directory.to_string_lossy(), &target_directory.to_string_lossy(), &Config::default(), &EpisodeCounts::default(), &Overrides::default(), &FilterWords::load(), &FileExtensions::load()).unwrap();
+        let mut target_paths: Vec<String> = plan.entries.iter()
+            .map(|entry| entry.target.strip_prefix(&target_directory).unwrap().to_string_lossy().to_string())
+            .collect();
+        target_paths.sort();
+        assert_eq!(target_paths, [
+            "Fruits Basket (2001)/Season 01/Fruits Basket - S01E01.mkv",
+            "Fruits Basket (2019)/Season 01/Fruits Basket - S01E01.mkv",
+            "Yuru Camp/Season 00/Yuru Camp - S00E01.mkv",
+            "Yuru Camp/Season 01/Yuru Camp - S01E01.mkv",
+            "Yuru Camp/Season 01/Yuru Camp - S01E02.mkv",
+            "Yuru Camp/Season 02/Yuru Camp - S02E01.mkv",
+            "Yuru Camp/Season 02/Yuru Camp - S02E02.mkv",
+        ]);
+        assert_eq!(plan.unresolved, [source_directory.join("[Kamigami] Yuru Camp [01-12][1080p]/[Kamigami] Yuru Camp [01][1080p].mkv")]);
+
+        let _ = fs::remove_dir_all(&test_directory);
+    }
+
+    #[test]
+    fn plan_duplicate_targets() {
+        // Setup
+        let _ = env_logger::builder().is_test(true).try_init();
+        let test_directory = std::env::temp_dir().join(format!("anime-organizer-rs-plan-duplicates-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&test_directory);
+        let source_directory = test_directory.join("source");
+        let target_directory = test_directory.join("target");
+
+        // Two releases of one episode, and subtitles told apart by language only
+        let file_names = [
+            "Show/Show - 01.mkv",
+            "Show/Show - 01.sc.ass",
+            "Show/Show - 01.tc.ass",
+            "Show/Show - 02 [Alt].mkv",
+            "Show/Show - 02.mkv",
+        ];
+        for file_name in file_names {
+            let path = source_directory.join(file_name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, file_name).unwrap();
+        }
+        let mut config = Config::default();
+        config.naming.template = Some("{series}/{series} - {episode:02}.{ext}".to_string());
+
+        // Run test
+        let mut plan = Plan::build(&source_directory.to_string_lossy(), &target_directory.to_string_lossy(), &config, &EpisodeCounts::default(), &Overrides::default(), &FilterWords::load(), &FileExtensions::load()).unwrap();
+        let mut target_paths: Vec<String> = plan.entries.iter()
+            .map(|entry| entry.target.strip_prefix(&target_directory).unwrap().to_string_lossy().to_string())
+            .collect();
+        target_paths.sort();
+        assert_eq!(target_paths, [
+            "Show/Show - 01.mkv",
+            "Show/Show - 01.zh-Hans.ass",
+            "Show/Show - 01.zh-Hant.ass",
+            "Show/Show - 02.mkv",
+        ]);
+        assert_eq!(plan.unresolved, [source_directory.join("Show/Show - 02.mkv")]);
+
+        // Applying again skips what the journal knows, a target taken by another file fails
+        let journal = Journal::open(&target_directory.join(JOURNAL_FILE_NAME));
+        assert_eq!(plan.apply(&journal), 0);
+        assert_eq!(plan.apply(&journal), 0);
+        plan.entries[0].source = source_directory.join("Show/Show - 02.mkv");
+        assert_eq!(plan.apply(&journal), 1);
+
+        let _ = fs::remove_dir_all(&test_directory);
+    }
+}
@@ -1,164 +1,1441 @@
-use core::panic;
 use std::fs;
-use std::fs::File;
-use std::io::Read;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
-use std::num::ParseIntError;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::LazyLock;
 use log::debug;
 use log::error;
 use log::warn;
 use log::info;
 use regex::Regex;
+use regex::RegexSet;
 use serde::Deserialize;
+use serde::Serialize;
 use std::result::Result;
 
-#[derive(Debug, Deserialize)]
+use crate::materialize::LinkMode;
+use crate::naming::Naming;
+use crate::naming::NamingFields;
+use crate::overrides::glob_match;
+use crate::overrides::Overrides;
+use crate::overrides::SeriesOverride;
+use crate::plan::PlanEntry;
+use crate::plan::REVIEW_CONFIDENCE;
+
+#[derive(Debug)]
 pub struct FilterWords {
-    cc_group: Vec<String>,
-    meta_tag: Vec<String>,
-    low_priority: Vec<String>,
+    lists: FilterWordLists,
+    /// All CC names in one alternation, None when there are none
+    cc_group_regex: Option<Regex>,
+    /// All Meta tags in one alternation, None when there are none
+    meta_tag_regex: Option<Regex>,
+    /// Finder of every CC name
+    cc_group_finders: Vec<Regex>,
+    /// Finder of every low priority word with the word it was built from
+    low_priority_finders: HashMap<EpisodeType, Vec<(FilterWord, Regex)>>,
+}
+
+/// Filter words as written in YAML, compiled into FilterWords
+#[derive(Debug, Clone, Deserialize)]
+struct FilterWordLists {
+    cc_group: Vec<FilterWord>,
+    meta_tag: Vec<FilterWord>,
+    low_priority: HashMap<EpisodeType, Vec<FilterWord>>,
+}
+
+/// A filter word matched case insensitively as a whole word, or a regex matched as written like `regex: 'NC(?:OP|ED)\d*'`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum FilterWord {
+    Word(String),
+    Regex { regex: String },
+}
+
+impl FilterWord {
+    /// Regex matching the entry
+    fn pattern(&self) -> String {
+        match self {
+            FilterWord::Word(word) => regex::escape(word),
+            FilterWord::Regex { regex } => regex.clone(),
+        }
+    }
+
+    /// Regex capturing the text matched by the entry in group 1, words only match as a whole word
+    fn finder(&self) -> Result<Regex, regex::Error> {
+        match self {
+            FilterWord::Word(_) => Regex::new(&format!(r"(?i)(?:^|[^[:alpha:]])({})(?:[^[:alpha:]]|$)", self.pattern())),
+            FilterWord::Regex { regex } => Regex::new(&format!(r"(?i)({})", regex)),
+        }
+    }
+
+    /// Same entry, words compare case insensitively
+    fn is_same(&self, other: &FilterWord) -> bool {
+        match (self, other) {
+            (FilterWord::Word(word), FilterWord::Word(other)) => word.eq_ignore_ascii_case(other),
+            (FilterWord::Regex { regex }, FilterWord::Regex { regex: other }) => regex == other,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for FilterWord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterWord::Word(word) => write!(f, "{}", word),
+            FilterWord::Regex { regex } => write!(f, "regex: {}", regex),
+        }
+    }
+}
+
+/// Entries added to and removed from one list of filter words
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilterWordChanges {
+    pub add: Vec<FilterWord>,
+    pub remove: Vec<FilterWord>,
+}
+
+/// User changes to the filter words, from a file in a config folder or the filter_words key of the config file
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilterWordsChanges {
+    pub cc_group: FilterWordChanges,
+    pub meta_tag: FilterWordChanges,
+    pub low_priority: HashMap<EpisodeType, FilterWordChanges>,
+}
+
+impl FilterWordsChanges {
+    pub fn load(file_path: &Path) -> Result<FilterWordsChanges, ()> {
+        let changes_string = match fs::read_to_string(file_path) {
+            Ok(content) => content,
+            Err(e) => {
+                error!("Failed to load filter words, please check {}, due to {}", file_path.display(), &e);
+                return Err(());
+            }
+        };
+
+        match serde_yaml::from_str(&changes_string) {
+            Ok(content) => {
+                info!("Load filter words {} successfully.", file_path.display());
+                Ok(content)
+            },
+            Err(err) => {
+                error!("Error parsing YAML {}: {}", file_path.display(), err);
+                Err(())
+            }
+        }
+    }
+}
+
+/// Apply changes to one list of filter words
+fn change_filter_words(words: &mut Vec<FilterWord>, changes: &FilterWordChanges, category: &str, origin: &str) {
+    for word in &changes.remove {
+        let count = words.len();
+        words.retain(|i| !i.is_same(word));
+        if words.len() == count {
+            warn!("Cannot remove {} from {} in {}, not in the list", &word, &category, &origin);
+        }
+    }
+    for word in &changes.add {
+        if !words.iter().any(|i| i.is_same(word)) {
+            words.push(word.clone());
+        }
+    }
 }
 
+/// Filter words shipped with the organizer, built into the binary
+const BUNDLED_FILTER_WORDS: &str = include_str!("../static/filter_words.yaml");
+
+/// File extensions shipped with the organizer, built into the binary
+const BUNDLED_FILE_EXTENSIONS: &str = include_str!("../static/file_extensions.yaml");
+
 impl FilterWords {
+    /// Shipped filter words
     pub fn load() -> FilterWords {
-        // TODO: Cache Load function
-        let filter_words_file_path = "./static/filter_words.yaml";
-        let mut filter_words_string = String::new();
-        match File::open(filter_words_file_path).unwrap().read_to_string(&mut filter_words_string) {
-            Ok(_) => info!("Load filter words successfully."),
-            Err(_) => error!("Failed to load filter words, please check {}", &filter_words_file_path),
+        // Test covered
+        let lists = serde_yaml::from_str(BUNDLED_FILTER_WORDS).expect("Bundled filter words are not valid YAML");
+        FilterWords::compile(lists, "bundled filter words").expect("Bundled filter words are not valid regexes")
+    }
+
+    /// Filter words replacing the shipped ones
+    pub fn load_from(filter_words_file_path: &str) -> Result<FilterWords, ()> {
+        let filter_words_string = match fs::read_to_string(filter_words_file_path) {
+            Ok(content) => {
+                info!("Load filter words successfully.");
+                content
+            },
+            Err(e) => {
+                error!("Failed to load filter words, please check {}, due to {}", &filter_words_file_path, &e);
+                return Err(());
+            }
         };
-    
-        // Parse the YAML content into the ServerConfig struct
-        match serde_yaml::from_str(&filter_words_string) {
+
+        match serde_yaml::from_str::<FilterWordLists>(&filter_words_string) {
             Ok(content) => {
                 info!("Parse filter words successfully.");
+                FilterWords::compile(content, filter_words_file_path)
+            },
+            Err(err) => {
+                error!("Error parsing YAML {}: {}", &filter_words_file_path, err);
+                Err(())
+            }
+        }
+    }
+
+    /// Add and remove entries of every list, origin names where the changes come from.
+    /// Nothing changes when an added regex is invalid.
+    pub fn apply(&mut self, changes: &FilterWordsChanges, origin: &str) -> Result<(), ()> {
+        let mut lists = self.lists.clone();
+        change_filter_words(&mut lists.cc_group, &changes.cc_group, "cc_group", origin);
+        change_filter_words(&mut lists.meta_tag, &changes.meta_tag, "meta_tag", origin);
+        for (episode_type, episode_type_changes) in &changes.low_priority {
+            let words = lists.low_priority.entry(*episode_type).or_default();
+            change_filter_words(words, episode_type_changes, &format!("low_priority.{:?}", episode_type).to_lowercase(), origin);
+        }
+        *self = FilterWords::compile(lists, origin)?;
+        Ok(())
+    }
+
+    /// Compile the regexes used on every file name, failing on any invalid regex entry
+    fn compile(lists: FilterWordLists, origin: &str) -> Result<FilterWords, ()> {
+        let mut result = Ok(());
+        let mut finder = |word: &FilterWord| match word.finder() {
+            Ok(reg) => Some(reg),
+            Err(e) => {
+                error!("Invalid filter word {} in {}, due to {}", &word, &origin, e);
+                result = Err(());
+                None
+            }
+        };
+        let cc_group_finders: Vec<Regex> = lists.cc_group.iter().filter_map(&mut finder).collect();
+        let meta_tag_finders: Vec<Regex> = lists.meta_tag.iter().filter_map(&mut finder).collect();
+        let low_priority_finders = lists.low_priority.iter()
+            .map(|(episode_type, words)| {
+                let finders = words.iter().filter_map(|word| Some((word.clone(), finder(word)?))).collect();
+                (*episode_type, finders)
+            })
+            .collect();
+        result?;
+        // Entries are valid on their own here, so the merged alternations compile too
+        let merged = |words: &[FilterWord]| {
+            if words.is_empty() {
+                return None;
+            }
+            let combined = words.iter().map(|i| format!("({})", i.pattern())).collect::<Vec<String>>().join("|");
+            Some(Regex::new(&format!(r"(?i){}(&{})*?", combined, combined)).expect("Invalid regex pattern"))
+        };
+        debug!("Compiled {} CC names and {} Meta tags from {}", cc_group_finders.len(), meta_tag_finders.len(), &origin);
+        Ok(FilterWords {
+            cc_group_regex: merged(&lists.cc_group),
+            meta_tag_regex: merged(&lists.meta_tag),
+            cc_group_finders,
+            low_priority_finders,
+            lists,
+        })
+    }
+}
+
+/// File kinds by extension name, loaded from YAML
+#[derive(Debug, Deserialize)]
+pub struct FileExtensions(HashMap<FileExtensionNames, Vec<String>>);
+
+impl FileExtensions {
+    /// Shipped file extensions
+    pub fn load() -> FileExtensions {
+        serde_yaml::from_str(BUNDLED_FILE_EXTENSIONS).expect("Bundled file extensions are not valid YAML")
+    }
+
+    /// File extensions replacing the shipped ones
+    pub fn load_from(file_extensions_file_path: &str) -> Result<FileExtensions, ()> {
+        let file_extensions_string = match fs::read_to_string(file_extensions_file_path) {
+            Ok(content) => {
+                info!("Load file extensions successfully.");
                 content
             },
+            Err(e) => {
+                error!("Failed to load file extensions, please check {}, due to {}", &file_extensions_file_path, &e);
+                return Err(());
+            }
+        };
+
+        match serde_yaml::from_str(&file_extensions_string) {
+            Ok(content) => {
+                info!("Parse file extensions successfully.");
+                Ok(content)
+            },
             Err(err) => {
-                error!("Error parsing YAML: {}", err);
-                panic!();
+                error!("Error parsing YAML {}: {}", &file_extensions_file_path, err);
+                Err(())
             }
         }
     }
 }
 
+/// Episode count of every season by series title or name, kept by the user for shows released with absolute numbers
+#[derive(Debug, Default, Deserialize)]
+pub struct EpisodeCounts(HashMap<String, Vec<u16>>);
+
+impl EpisodeCounts {
+    pub fn load(file_path: &str) -> Result<EpisodeCounts, ()> {
+        let episode_counts_string = match fs::read_to_string(file_path) {
+            Ok(content) => content,
+            Err(e) => {
+                error!("Failed to load episode counts, please check {}, due to {}", &file_path, &e);
+                return Err(());
+            }
+        };
 
-pub fn create_folder(full_path: &str) {
-    if !fs::metadata(&full_path).is_ok() {
-        fs::create_dir_all(&full_path).unwrap();
-        info!("Created target folder successfully.")
-    } else {
-        info!("Target folder already exists. Skipping the creation.")
+        match serde_yaml::from_str::<EpisodeCounts>(&episode_counts_string) {
+            Ok(content) => {
+                info!("Load episode counts of {} series successfully.", content.0.len());
+                Ok(content)
+            },
+            Err(err) => {
+                error!("Error parsing YAML {}: {}", &file_path, err);
+                Err(())
+            }
+        }
+    }
+
+    /// Episode counts of a series, looked up by title with year first
+    fn get(&self, series: &Series) -> Option<&Vec<u16>> {
+        [series.title(), series.name.clone()].iter()
+            .find_map(|key| self.0.iter().find(|(name, _)| name.eq_ignore_ascii_case(key)))
+            .map(|(_, episode_counts)| episode_counts)
     }
 }
 
 pub struct Series {
     name: String,
+    /// Release year, telling remakes sharing a name apart
+    year: Option<u16>,
     location: PathBuf,
+    /// Release details found in the folder name
+    release: ReleaseInfo,
     seasons: Vec<Season>,
+    /// Episode count of every season, known from episode counts file
+    episode_counts: Vec<u16>,
+    /// Fields forced by overrides instead of extracted, like "name"
+    overridden: Vec<&'static str>,
 }
 
 struct Season {
-    sequence: i8,
+    sequence: u16,
     location: String,
     episodes: Vec<Episode>,
+    /// Files whose episode number could not be found
+    unresolved: Vec<String>,
 }
 
 pub struct Episode {
     sequence: u16,
+    /// Last episode of a multi-episode file, like 2 in 01-02 or S01E01E02
+    last_sequence: Option<u16>,
+    episode_type: EpisodeType,
     location: String,
     subtitles: Vec<Subtitle>,
+    /// External audio tracks and chapters sharing the episode file stem
+    attachments: Vec<Attachment>,
+    confidence: f32,
+    reason: String,
+    /// Rule which picked the episode number, None for episodes numbered otherwise
+    rule: Option<EpisodeNumberRule>,
+    /// Every episode number found in the file name, ranked by score
+    candidates: Vec<EpisodeCandidate>,
+    /// Release details found in the file name, completed by the series ones
+    release: ReleaseInfo,
+    /// Revision of the release, 2 for 07v2
+    version: u8,
+    /// Marked as the last episode, like 12END or 13 Fin
+    finale: bool,
+    /// Fields forced by overrides instead of extracted, like "episode"
+    overridden: Vec<&'static str>,
 }
 
 impl Default for Episode {
     fn default() -> Self {
         Self {
             sequence: 0,
+            last_sequence: None,
+            episode_type: EpisodeType::Main,
             location: "".to_string(),
-            subtitles: Vec::new()
+            subtitles: Vec::new(),
+            attachments: Vec::new(),
+            confidence: 0.0,
+            reason: "".to_string(),
+            rule: None,
+            candidates: Vec::new(),
+            release: ReleaseInfo::default(),
+            version: 1,
+            finale: false,
+            overridden: Vec::new(),
         }
     }
 }
 
+/// How an episode number was picked among the numbers in a file name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EpisodeNumberRule {
+    /// The file name holds a single number
+    OnlyCandidate,
+    /// First of several numbers, no other file to compare with
+    FirstCandidate,
+    /// Number shared by the fewest files of the same kind
+    LeastFrequent,
+    /// Number continuing the episodes of the previous file in name order, over a weak least frequent pick
+    SequenceRun,
+}
+
+impl fmt::Display for EpisodeNumberRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            EpisodeNumberRule::OnlyCandidate => "only-candidate",
+            EpisodeNumberRule::FirstCandidate => "first-candidate",
+            EpisodeNumberRule::LeastFrequent => "least-frequent",
+            EpisodeNumberRule::SequenceRun => "sequence-run",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Episode number found in a file name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EpisodeCandidate {
+    pub number: u16,
+    /// Share of files of the same kind containing the number, the lowest wins and ties go to the earliest
+    pub score: f32,
+}
+
+/// Kind of numbering problem found in a season
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SeasonIssueKind {
+    /// Episodes absent between the first and last episode
+    Missing,
+    /// Several files numbered as the same episode
+    Duplicate,
+    /// Episode number far beyond the rest of the season, likely picked wrong
+    Outlier,
+}
+
+/// Numbering problem found in a season, worth checking by hand
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeasonIssue {
+    pub series: String,
+    pub season: u16,
+    pub kind: SeasonIssueKind,
+    pub episode_type: EpisodeType,
+    pub episodes: Vec<u16>,
+    /// Files involved, empty for missing episodes
+    pub locations: Vec<PathBuf>,
+}
+
+impl fmt::Display for SeasonIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let episodes: Vec<String> = self.episodes.iter().map(|episode| episode.to_string()).collect();
+        write!(f, "{:?} {:?} episodes {} in season {} of {}", &self.kind, &self.episode_type, episodes.join(", "), &self.season, &self.series)?;
+        for location in &self.locations {
+            write!(f, ", {}", location.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of episode number extraction for a single file
+pub enum EpisodeNumber {
+    Resolved(Box<Episode>),
+    /// No episode number in the file name
+    Unresolved(String),
+}
+
 struct Subtitle {
     language: String,
+    /// Only covers signs and foreign dialogue
+    forced: bool,
+    location: String,
+}
+
+struct Attachment {
+    /// Part of file name between episode stem and extension, like "jpn" in "Episode.jpn.mka"
+    suffix: String,
     location: String,
 }
 
 impl Series {
-    pub fn new(folder_path: &str) -> Result<Series, ()> {
+    /// Resolve a source folder, files matching ignore globs are left out
+    pub fn new(folder_path: &str, split_cour: SplitCour, overrides: &Overrides, ignore: &[String], filter_words: &FilterWords, file_extensions: &FileExtensions) -> Result<Series, ()> {
         // Entry point for Series struct
         debug!("Folder path: {}", &folder_path);
 
-        let filter_words = FilterWords::load();
-
         let mut series = Series {
             name: "".to_string(),
+            year: None,
             location: PathBuf::from(&folder_path),
+            release: ReleaseInfo::default(),
             seasons: Vec::new(),
+            episode_counts: Vec::new(),
+            overridden: Vec::new(),
         };
 
         let folder_name = match series.location.file_name() {
             Some(name) => {
                 debug!("Folder name: {}", &name.to_string_lossy());
-                name
+                name.to_string_lossy().to_string()
             },
             None => {
                 warn!("Failed to get folder name.");
-                panic!("")
+                return Err(());
             }
         };
 
-        series.name = match extract_series_name(&folder_name.to_string_lossy(), &filter_words) {
-            Ok(name) => {
+        let series_override = overrides.get(&series.location)?;
+
+        series.name = match (&series_override.name, extract_series_name(&folder_name, filter_words)) {
+            (Some(name), _) => {
+                info!("Series name: {}, overridden", &name);
+                series.overridden.push("name");
+                name.clone()
+            },
+            (None, Ok(name)) => {
                 info!("Series name: {}", &name);
                 name
             },
-            Err(e) => {
+            (None, Err(_)) => {
                 warn!("Failed to extract series name");
-                panic!("")
+                return Err(());
+            }
+        };
+
+        series.year = match series_override.year {
+            Some(year) => {
+                series.overridden.push("year");
+                Some(year)
+            },
+            None => extract_series_year(&folder_name, filter_words),
+        };
+        if let Some(year) = series.year {
+            info!("Series year: {}", &year);
+        }
+
+        let season_number = match (series_override.season, extract_series_season_number(&folder_name, filter_words, split_cour)) {
+            (Some(season_number), _) => {
+                info!("Season number: {}, overridden", &season_number);
+                series.overridden.push("season");
+                season_number
+            },
+            (None, Ok(season_number)) => {
+                info!("Season number: {}", &season_number);
+                season_number
+            },
+            (None, Err(_)) => {
+                warn!("Failed to extract season number");
+                return Err(());
+            }
+        };
+
+        series.release = extract_release_info(&folder_name, filter_words);
+        info!("Release: {}", &series.release);
+
+        // Batches may hold several seasons, in season folders or marked on each file
+        let mut season_file_names = HashMap::<u16, Vec<String>>::new();
+        for file_name in walk_folder(Path::new(folder_path))? {
+            if series_override.is_ignored(&file_name) {
+                info!("Ignoring {}, as overridden", &file_name);
+                continue;
+            }
+            if ignore.iter().any(|pattern| glob_match(pattern, &file_name)) {
+                info!("Ignoring {}, as configured", &file_name);
+                continue;
             }
+            let sequence = match series_override.season {
+                Some(_) => season_number,
+                None => extract_file_season_number(&file_name, filter_words, split_cour).unwrap_or(season_number),
+            };
+            season_file_names.entry(sequence).or_default().push(file_name);
+        }
+        let mut season_file_names: Vec<(u16, Vec<String>)> = season_file_names.into_iter().collect();
+        season_file_names.sort();
+        for (sequence, file_names) in season_file_names {
+            series.seasons.push(Season::new(folder_path, sequence, file_names, filter_words, file_extensions, &series_override)?);
+        }
+
+        // A folder of specials, like "Show OVA", numbers its episodes as specials
+        if extract_folder_episode_type(&folder_name, filter_words) == EpisodeType::Special {
+            info!("{} only holds specials", &folder_name);
+            for season in &mut series.seasons {
+                for episode in season.episodes.iter_mut().filter(|episode| episode.episode_type == EpisodeType::Main) {
+                    episode.episode_type = EpisodeType::Special;
+                }
+                season.episodes.sort_by_key(|episode| (episode.episode_type, episode.sequence));
+            }
+        }
+
+        // Files usually repeat the folder tags, fill in what they leave out
+        for episode in series.seasons.iter_mut().flat_map(|season| season.episodes.iter_mut()) {
+            episode.release = episode.release.merge(&series.release);
+        }
+
+        Ok(series)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn year(&self) -> Option<u16> {
+        self.year
+    }
+
+    /// Move main episodes of seasons numbered absolutely, like episode 25 of a 2-cour show, to their own season
+    pub fn renumber(&mut self, episode_counts: &EpisodeCounts) {
+        let episode_counts = match episode_counts.get(self) {
+            Some(episode_counts) => episode_counts.clone(),
+            None => return,
         };
+        debug!("Episode counts of {}: {:?}", self.title(), &episode_counts);
+
+        let mut moved_episodes = Vec::<(u16, Episode)>::new();
+        for season in &mut self.seasons {
+            let episode_count = match (season.sequence as usize).checked_sub(1).and_then(|index| episode_counts.get(index)) {
+                Some(episode_count) => *episode_count,
+                None => continue,
+            };
+            let is_absolute = season.episodes.iter().any(|episode| episode.episode_type == EpisodeType::Main && episode.sequence > episode_count);
+            if !is_absolute {
+                continue;
+            }
+            info!("Season {} of {} is numbered absolutely", &season.sequence, self.name);
+
+            let (main_episodes, other_episodes) = std::mem::take(&mut season.episodes).into_iter()
+                .partition(|episode| episode.episode_type == EpisodeType::Main);
+            season.episodes = other_episodes;
+            for mut episode in main_episodes {
+                let (sequence, episode_number) = absolute_to_seasonal(episode.sequence, &episode_counts);
+                debug!("Absolute episode {} is season {} episode {}", &episode.sequence, &sequence, &episode_number);
+                episode.reason = format!("{}, absolute episode {} mapped by episode counts", &episode.reason, &episode.sequence);
+                episode.last_sequence = episode.last_sequence.map(|last_sequence| last_sequence + episode_number - episode.sequence);
+                episode.sequence = episode_number;
+                moved_episodes.push((sequence, episode));
+            }
+        }
+
+        for (sequence, episode) in moved_episodes {
+            let index = match self.seasons.iter().position(|season| season.sequence == sequence) {
+                Some(index) => index,
+                None => {
+                    self.seasons.push(Season {
+                        sequence,
+                        location: self.location.to_string_lossy().to_string(),
+                        episodes: Vec::new(),
+                        unresolved: Vec::new(),
+                    });
+                    self.seasons.len() - 1
+                }
+            };
+            self.seasons[index].episodes.push(episode);
+        }
+        for season in &mut self.seasons {
+            season.episodes.sort_by_key(|episode| (episode.episode_type, episode.sequence));
+        }
+        self.seasons.retain(|season| !season.episodes.is_empty() || !season.unresolved.is_empty());
+        self.seasons.sort_by_key(|season| season.sequence);
+        self.episode_counts = episode_counts;
+    }
+
+    /// Take over seasons of another folder of the same show, episodes already present are kept
+    pub fn merge(&mut self, other: Series) {
+        info!("Merging {} into {}", other.location.display(), self.location.display());
+        if self.year.is_none() {
+            self.year = other.year;
+            if other.overridden.contains(&"year") {
+                self.overridden.push("year");
+            }
+        }
+
+        for other_season in other.seasons {
+            let season = match self.seasons.iter_mut().find(|season| season.sequence == other_season.sequence) {
+                Some(season) => season,
+                None => {
+                    self.seasons.push(other_season);
+                    continue;
+                }
+            };
+            season.unresolved.extend(other_season.unresolved);
+
+            for episode in other_season.episodes {
+                let numbered = matches!(episode.episode_type, EpisodeType::Main | EpisodeType::Special);
+                let duplicate = season.episodes.iter().any(|i| i.episode_type == episode.episode_type && i.sequence == episode.sequence);
+                if numbered && duplicate {
+                    warn!("{:?} episode {} of season {} already exists, leaving {} unresolved", &episode.episode_type, &episode.sequence, &season.sequence, &episode.location);
+                    season.unresolved.push(episode.location.clone());
+                    season.unresolved.extend(episode.subtitles.iter().map(|subtitle| subtitle.location.clone()));
+                    season.unresolved.extend(episode.attachments.iter().map(|attachment| attachment.location.clone()));
+                    continue;
+                }
+                season.episodes.push(episode);
+            }
+            season.episodes.sort_by_key(|episode| (episode.episode_type, episode.sequence));
+        }
+        self.seasons.sort_by_key(|season| season.sequence);
+    }
 
-        todo!()
+    /// Look for missing, duplicate and outlier episodes in every season
+    pub fn check(&self) -> Vec<SeasonIssue> {
+        self.seasons.iter()
+            .flat_map(|season| season.check(&self.title()))
+            .collect()
+    }
+
+    /// Files of every season whose episode number could not be found
+    pub fn unresolved(&self) -> Vec<PathBuf> {
+        self.seasons.iter()
+            .flat_map(|season| season.unresolved.iter().map(PathBuf::from))
+            .collect()
+    }
+
+    /// Name with year when known, like "Fruits Basket (2019)"
+    pub fn title(&self) -> String {
+        match self.year {
+            Some(year) => format!("{} ({})", &self.name, year),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Pairs every video and sidecar file in the series with its path in the target library
+    pub fn plan(&self, target_directory: &str, action: LinkMode, naming: &Naming) -> Vec<PlanEntry> {
+        let series_name = string_sanitize_path(&self.name);
+        debug!("Planning {} to {}", self.location.display(), &target_directory);
+
+        let mut result = Vec::new();
+        for season in &self.seasons {
+            debug!("Planning season {} in {}", &season.sequence, &season.location);
+            for episode in &season.episodes {
+                let original_name = string_remove_file_extension(&string_get_file_name(&episode.location));
+                let overridden: Vec<String> = self.overridden.iter().chain(&episode.overridden).map(|field| field.to_string()).collect();
+                let fields = NamingFields {
+                    series: series_name.clone(),
+                    year: self.year,
+                    season: season.sequence,
+                    episode_type: episode.episode_type,
+                    episode: episode.sequence,
+                    last_episode: episode.last_sequence,
+                    absolute: match episode.episode_type {
+                        EpisodeType::Main => seasonal_to_absolute(season.sequence, episode.sequence, &self.episode_counts),
+                        _ => None,
+                    },
+                    group: Some(episode.release.groups.join("&")),
+                    resolution: episode.release.resolution.clone(),
+                    codec: episode.release.video_codec.clone(),
+                    crc: string_find_crc(&original_name),
+                    ext: string_get_file_extension(&episode.location).unwrap_or_default(),
+                    original_name,
+                    ..Default::default()
+                };
+                result.push(PlanEntry {
+                    series: self.title(),
+                    source: PathBuf::from(&episode.location),
+                    target: Path::new(target_directory).join(naming.target_path(&fields)),
+                    action,
+                    confidence: episode.confidence,
+                    reason: episode.reason.clone(),
+                    rule: episode.rule,
+                    candidates: episode.candidates.clone(),
+                    review: episode.confidence < REVIEW_CONFIDENCE,
+                    overridden: overridden.clone(),
+                });
+
+                // Only the first subtitle in preferred language is flagged as default
+                let default_subtitle = episode.subtitles.iter()
+                    .position(|subtitle| Some(&subtitle.language) == naming.default_subtitle_language.as_ref());
+                for (index, subtitle) in episode.subtitles.iter().enumerate() {
+                    let suffix = naming.subtitle_suffix(&subtitle.language, Some(index) == default_subtitle, subtitle.forced);
+                    let fields = NamingFields {
+                        lang: Some(suffix.trim_start_matches('.').to_string()),
+                        ext: string_get_file_extension(&subtitle.location).unwrap_or_default(),
+                        ..fields.clone()
+                    };
+                    result.push(PlanEntry {
+                        series: self.title(),
+                        source: PathBuf::from(&subtitle.location),
+                        target: Path::new(target_directory).join(naming.target_path(&fields)),
+                        action,
+                        confidence: episode.confidence,
+                        reason: format!("Subtitle of {:?} episode {}", &episode.episode_type, &episode.sequence),
+                        rule: None,
+                        candidates: Vec::new(),
+                        review: episode.confidence < REVIEW_CONFIDENCE,
+                        overridden: overridden.clone(),
+                    });
+                }
+
+                for attachment in &episode.attachments {
+                    let fields = NamingFields {
+                        lang: Some(attachment.suffix.clone()),
+                        ext: string_get_file_extension(&attachment.location).unwrap_or_default(),
+                        ..fields.clone()
+                    };
+                    result.push(PlanEntry {
+                        series: self.title(),
+                        source: PathBuf::from(&attachment.location),
+                        target: Path::new(target_directory).join(naming.target_path(&fields)),
+                        action,
+                        confidence: episode.confidence,
+                        reason: format!("Attachment sharing file stem with {:?} episode {}", &episode.episode_type, &episode.sequence),
+                        rule: None,
+                        candidates: Vec::new(),
+                        review: episode.confidence < REVIEW_CONFIDENCE,
+                        overridden: overridden.clone(),
+                    });
+                }
+            }
+        }
+
+        result
     }
 }
 
+/// Episode numbers at least this far past the next highest one are reported as outliers
+const OUTLIER_GAP: u16 = 12;
+
+impl fmt::Display for Series {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.title())?;
+        for season in &self.seasons {
+            let mut counts = BTreeMap::<EpisodeType, usize>::new();
+            for episode in &season.episodes {
+                *counts.entry(episode.episode_type).or_default() += 1;
+            }
+            let mut counts: Vec<String> = counts.iter()
+                .map(|(episode_type, count)| format!("{} {:?}", count, episode_type).to_lowercase())
+                .collect();
+            if !season.unresolved.is_empty() {
+                counts.push(format!("{} unresolved", season.unresolved.len()));
+            }
+            write!(f, "\n  Season {:02}: {}", &season.sequence, counts.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Season {
+    /// Resolve episodes of a season from file names relative to the series folder
+    fn new(folder_path: &str, sequence: u16, file_names: Vec<String>, filter_words: &FilterWords, file_extensions: &FileExtensions, series_override: &SeriesOverride) -> Result<Season, ()> {
+        debug!("Resolving season {} in {}", &sequence, &folder_path);
+
+        // Sort files into videos and sidecars
+        let mut video_names = HashMap::<EpisodeType, Vec<String>>::new();
+        let mut sidecar_names = Vec::<(FileExtensionNames, String)>::new();
+        let mut typed_names = HashSet::<String>::new();
+        for file_name in file_names {
+            match extract_file_extension(&file_name, file_extensions) {
+                Some(FileExtensionNames::Video) => {
+                    let episode_type = match series_override.episode_type(&file_name) {
+                        Some(episode_type) => {
+                            debug!("Classified {} as {:?} by overrides", &file_name, &episode_type);
+                            typed_names.insert(file_name.clone());
+                            episode_type
+                        },
+                        None => extract_episode_type(&file_name, filter_words),
+                    };
+                    video_names.entry(episode_type).or_default().push(file_name);
+                },
+                Some(kind @ (FileExtensionNames::Subtitle | FileExtensionNames::Audio | FileExtensionNames::Chapter)) => sidecar_names.push((kind, file_name)),
+                Some(kind) => debug!("Skipping {:?} file {}", &kind, &file_name),
+                None => debug!("Skipping unrecognized file {}", &file_name),
+            }
+        }
+
+        // Number main episodes and specials separately, so extras do not pollute episode numbering
+        let mut episodes = Vec::<Episode>::new();
+        let mut unresolved = Vec::<String>::new();
+        let mut decimal_names = Vec::<(String, String)>::new();
+        for episode_type in EpisodeType::ORDER {
+            let file_names = match video_names.remove(&episode_type) {
+                Some(file_names) => file_names,
+                None => continue,
+            };
+            debug!("Found {} {:?} files", file_names.len(), &episode_type);
+
+            match episode_type {
+                EpisodeType::Main | EpisodeType::Special => {
+                    // Recaps like 12.5 sit between main episodes, they are numbered after the other specials
+                    let mut numbered_names = Vec::new();
+                    for file_name in file_names {
+                        match string_find_decimal_episode(&string_clean_episode_name(&file_name, filter_words)) {
+                            Some(decimal) => decimal_names.push((decimal, file_name)),
+                            None => numbered_names.push(file_name),
+                        }
+                    }
+
+                    for episode_number in extract_episode_number(numbered_names, filter_words) {
+                        let mut episode = match episode_number {
+                            EpisodeNumber::Resolved(episode) => *episode,
+                            EpisodeNumber::Unresolved(file_name) => match series_override.episode_number(&file_name) {
+                                Some(_) => Episode {
+                                    location: file_name,
+                                    ..Default::default()
+                                },
+                                None => {
+                                    unresolved.push(file_name);
+                                    continue;
+                                }
+                            },
+                        };
+                        episode.episode_type = episode_type;
+
+                        if let Some(sequence) = series_override.episode_number(&episode.location) {
+                            debug!("Episode number {} of {} by overrides", &sequence, &episode.location);
+                            episode.sequence = sequence;
+                            episode.last_sequence = None;
+                            episode.confidence = 1.0;
+                            episode.reason = "Episode number matched by override regex".to_string();
+                            episode.rule = None;
+                            episode.overridden.push("episode");
+                        }
+                        if let (EpisodeType::Main, Some(episode_offset)) = (episode_type, series_override.episode_offset) {
+                            match series_override.offset_episode(episode.sequence) {
+                                Some(sequence) => {
+                                    episode.reason = format!("{}, shifted by override offset {}", &episode.reason, &episode_offset);
+                                    episode.last_sequence = episode.last_sequence.and_then(|last_sequence| series_override.offset_episode(last_sequence));
+                                    episode.sequence = sequence;
+                                    if !episode.overridden.contains(&"episode") {
+                                        episode.overridden.push("episode");
+                                    }
+                                },
+                                None => warn!("Override offset {} would take episode {} of {} below zero", &episode_offset, &episode.sequence, &episode.location),
+                            }
+                        }
+                        episodes.push(episode);
+                    }
+                },
+                _ => {
+                    // Extras keep their original names
+                    for file_name in file_names {
+                        episodes.push(Episode {
+                            episode_type,
+                            location: file_name,
+                            confidence: 1.0,
+                            reason: format!("Classified as {:?} by keyword", &episode_type),
+                            ..Default::default()
+                        });
+                    }
+                },
+            }
+        }
+
+        let mut special_sequence = episodes.iter()
+            .filter(|episode| episode.episode_type == EpisodeType::Special)
+            .map(|episode| episode.sequence)
+            .max()
+            .unwrap_or_default();
+        for (decimal, file_name) in decimal_names {
+            special_sequence += 1;
+            debug!("Numbered recap episode {} as special {}", &decimal, &special_sequence);
+            episodes.push(Episode {
+                sequence: special_sequence,
+                episode_type: EpisodeType::Special,
+                location: file_name,
+                confidence: 1.0,
+                reason: format!("Decimal episode {} numbered after specials", &decimal),
+                ..Default::default()
+            });
+        }
+
+        // Keep only the highest version of revised releases, like 07v2 over 07
+        let mut highest_versions = HashMap::<(EpisodeType, u16), u8>::new();
+        for episode in episodes.iter().filter(|episode| matches!(episode.episode_type, EpisodeType::Main | EpisodeType::Special)) {
+            let version = highest_versions.entry((episode.episode_type, episode.sequence)).or_default();
+            *version = (*version).max(episode.version);
+        }
+        episodes.retain(|episode| match highest_versions.get(&(episode.episode_type, episode.sequence)) {
+            Some(version) if *version > episode.version => {
+                info!("Skipping {}, superseded by version {}", &episode.location, &version);
+                false
+            },
+            _ => true,
+        });
+        episodes.sort_by_key(|episode| (episode.episode_type, episode.sequence));
+
+        if let Some(finale) = episodes.iter().find(|episode| episode.episode_type == EpisodeType::Main && episode.finale) {
+            if episodes.iter().any(|episode| episode.episode_type == EpisodeType::Main && episode.sequence > finale.sequence) {
+                warn!("Episodes follow {} marked as the final episode", &finale.location);
+            }
+        }
+
+        // Pair sidecars with the episode sharing the same file stem
+        let mut unpaired_subtitle_names = Vec::<String>::new();
+        for (kind, sidecar_name) in sidecar_names {
+            let episode = episodes.iter_mut().find(|episode| {
+                let stem = string_remove_file_extension(&episode.location);
+                sidecar_name.starts_with(&stem) && sidecar_name[stem.len()..].starts_with('.')
+            });
+            let episode = match (episode, kind) {
+                (Some(episode), _) => episode,
+                (None, FileExtensionNames::Subtitle) => {
+                    debug!("Failed to pair subtitle {} by file stem", &sidecar_name);
+                    unpaired_subtitle_names.push(sidecar_name);
+                    continue;
+                },
+                (None, _) => {
+                    warn!("Failed to pair {:?} file {} with any episode", &kind, &sidecar_name);
+                    continue;
+                }
+            };
+
+            let stem = string_remove_file_extension(&episode.location);
+            let suffix = string_remove_file_extension(&sidecar_name[stem.len()..]).trim_matches('.').to_string();
+            let location = Path::new(folder_path).join(&sidecar_name).to_string_lossy().to_string();
+            debug!("Pair {:?} file {} with episode {}, suffix {}", &kind, &sidecar_name, &episode.sequence, &suffix);
+            match kind {
+                FileExtensionNames::Subtitle => {
+                    let forced = string_contains_word(&suffix, "forced");
+                    let language = extract_subtitle_language(&suffix).unwrap_or(suffix.to_lowercase());
+                    episode.subtitles.push(Subtitle { language, forced, location })
+                },
+                _ => episode.attachments.push(Attachment { suffix, location }),
+            }
+        }
+
+        // Pair the remaining subtitles with main episodes by episode number, like subtitles shipped in a separate folder
+        for subtitle in extract_episode_number(unpaired_subtitle_names, filter_words) {
+            let subtitle = match subtitle {
+                EpisodeNumber::Resolved(subtitle) => subtitle,
+                EpisodeNumber::Unresolved(file_name) => {
+                    unresolved.push(file_name);
+                    continue;
+                }
+            };
+            let episode = episodes.iter_mut().find(|episode| episode.episode_type == EpisodeType::Main && episode.sequence == subtitle.sequence);
+            match episode {
+                Some(episode) => {
+                    let language = extract_subtitle_language(&string_get_language_tags(&subtitle.location)).unwrap_or_default();
+                    debug!("Pair subtitle {} with episode {} by episode number, language {}", &subtitle.location, &episode.sequence, &language);
+                    episode.subtitles.push(Subtitle {
+                        language,
+                        forced: string_contains_word(&subtitle.location, "forced"),
+                        location: Path::new(folder_path).join(&subtitle.location).to_string_lossy().to_string(),
+                    });
+                },
+                None => warn!("Failed to pair subtitle {} with any episode", &subtitle.location),
+            }
+        }
+
+        // Episode location is only file name until now
+        for episode in &mut episodes {
+            if typed_names.contains(&episode.location) {
+                episode.overridden.push("type");
+            }
+            episode.release = extract_release_info(&string_get_file_name(&episode.location), filter_words);
+            episode.location = Path::new(folder_path).join(&episode.location).to_string_lossy().to_string();
+        }
+        for file_name in &mut unresolved {
+            *file_name = Path::new(folder_path).join(&file_name).to_string_lossy().to_string();
+        }
+
+        Ok(Season {
+            sequence,
+            location: folder_path.to_string(),
+            episodes,
+            unresolved,
+        })
+    }
+
+    /// Look for numbering problems among main episodes and specials.
+    /// Episodes are expected to run from the lowest to the highest number, so a season starting at 13 is fine.
+    fn check(&self, series_title: &str) -> Vec<SeasonIssue> {
+        let mut result = Vec::new();
+        for episode_type in [EpisodeType::Main, EpisodeType::Special] {
+            let episodes: Vec<&Episode> = self.episodes.iter().filter(|episode| episode.episode_type == episode_type).collect();
+            let issue = |kind, episodes: Vec<u16>, locations: Vec<PathBuf>| SeasonIssue {
+                series: series_title.to_string(),
+                season: self.sequence,
+                kind,
+                episode_type,
+                episodes,
+                locations,
+            };
+
+            // Several files claiming the same episode
+            let mut locations = BTreeMap::<u16, Vec<PathBuf>>::new();
+            for episode in &episodes {
+                locations.entry(episode.sequence).or_default().push(PathBuf::from(&episode.location));
+            }
+            for (sequence, locations) in locations {
+                if locations.len() > 1 {
+                    result.push(issue(SeasonIssueKind::Duplicate, vec![sequence], locations));
+                }
+            }
+
+            // A number far beyond the others, like 99 amid 1 to 12
+            let mut sequences: Vec<u16> = episodes.iter().map(|episode| episode.last_sequence.unwrap_or(episode.sequence)).collect();
+            sequences.sort();
+            sequences.dedup();
+            let mut outliers = Vec::new();
+            if sequences.len() >= 3 {
+                let highest = sequences[sequences.len() - 1];
+                let runner_up = sequences[sequences.len() - 2];
+                if highest > runner_up * 2 && highest - runner_up > OUTLIER_GAP {
+                    outliers.push(highest);
+                    let locations = episodes.iter()
+                        .filter(|episode| episode.last_sequence.unwrap_or(episode.sequence) == highest)
+                        .map(|episode| PathBuf::from(&episode.location))
+                        .collect();
+                    result.push(issue(SeasonIssueKind::Outlier, vec![highest], locations));
+                }
+            }
+
+            // Gaps between the lowest and highest episode, multi-episode files cover their whole range
+            let covered: HashSet<u16> = episodes.iter()
+                .filter(|episode| !outliers.contains(&episode.last_sequence.unwrap_or(episode.sequence)))
+                .flat_map(|episode| episode.sequence..=episode.last_sequence.unwrap_or(episode.sequence))
+                .collect();
+            if let (Some(lowest), Some(highest)) = (covered.iter().min(), covered.iter().max()) {
+                let missing: Vec<u16> = (*lowest..=*highest).filter(|sequence| !covered.contains(sequence)).collect();
+                if !missing.is_empty() {
+                    result.push(issue(SeasonIssueKind::Missing, missing, Vec::new()));
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Convert an absolute episode number into season and episode, given the episode count of every season.
+/// Episodes past the last known season continue it, like a running show.
+fn absolute_to_seasonal(absolute: u16, episode_counts: &[u16]) -> (u16, u16) {
+    // Test covered
+    let mut episode = absolute;
+    for (index, episode_count) in episode_counts.iter().enumerate() {
+        if episode <= *episode_count || index + 1 == episode_counts.len() {
+            return (index as u16 + 1, episode);
+        }
+        episode -= episode_count;
+    }
+    (1, absolute)
+}
+
+/// Convert season and episode into an absolute episode number, None when an earlier season has no known episode count
+fn seasonal_to_absolute(season: u16, episode: u16, episode_counts: &[u16]) -> Option<u16> {
+    // Test covered
+    let previous_seasons = episode_counts.get(..(season as usize).checked_sub(1)?)?;
+    Some(previous_seasons.iter().sum::<u16>() + episode)
+}
+
+/// List all files in folder and its sub folders, relative to the folder
+fn walk_folder(folder_path: &Path) -> Result<Vec<String>, ()> {
+    let mut result = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative_path) = pending.pop() {
+        let paths = match fs::read_dir(folder_path.join(&relative_path)) {
+            Ok(paths) => paths,
+            Err(e) => {
+                error!("Failed open {}, due to {}", folder_path.join(&relative_path).display(), &e);
+                return Err(());
+            }
+        };
+
+        for entry in paths.flatten() {
+            let path = relative_path.join(entry.file_name());
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => pending.push(path),
+                // Linked folders may loop back into the source directory
+                Ok(file_type) if file_type.is_symlink() && entry.path().is_dir() => warn!("Skipping linked folder {}", entry.path().display()),
+                _ => result.push(path.to_string_lossy().to_string()),
+            }
+        }
+    }
+    result.sort();
+
+    Ok(result)
+}
+
 pub fn extract_series_name(folder_name: &str, filter_words: &FilterWords) -> Result<String, ()> {
     // Test covered
-    // let mut result = string_remove_square_brackets(&string_remove_filtered(&folder_name).unwrap()).unwrap().trim().to_string();// basic_file_name_cleaning(&folder_name, &filter_words).unwrap(); // TODO: Use cache in struct
-    let mut result = {
-        let mut middleware = string_remove_filtered(&folder_name).unwrap();
-        middleware = string_remove_years(&middleware).unwrap();
-        middleware = string_remove_roman_number(&middleware).unwrap();
-        middleware = string_remove_episode_range(&middleware).unwrap();
-        middleware = string_remove_empty_brackets(&middleware).unwrap();
-        middleware = string_remove_duplicate_spaces(&middleware).unwrap();
-        middleware.trim().to_string()
+    let stages = clean_series_name(folder_name, filter_words, false);
+    Ok(stages.last().map(|stage| stage.result.clone()).unwrap_or_default())
+}
+
+/// A filter word or regex which matched while cleaning a name
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StageMatch {
+    pub pattern: String,
+    /// Text matched in the input of the stage
+    pub text: String,
+}
+
+/// Name after one cleaning stage of series name extraction
+#[derive(Debug, Clone, Serialize)]
+pub struct NameStage {
+    pub stage: &'static str,
+    pub result: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fired: Vec<StageMatch>,
+}
+
+/// Every cleaning stage of series name extraction in order, the last result is the series name
+pub fn trace_series_name(folder_name: &str, filter_words: &FilterWords) -> Vec<NameStage> {
+    // Test covered
+    clean_series_name(folder_name, filter_words, true)
+}
+
+/// Cleaning stages of series name extraction, what fired in each stage is only searched when explaining
+fn clean_series_name(folder_name: &str, filter_words: &FilterWords, explain: bool) -> Vec<NameStage> {
+    let mut stages = Vec::new();
+    let mut record = |stage: &'static str, result: String, fired: &dyn Fn() -> Vec<StageMatch>| {
+        debug!("After removing {}: {}", &stage, &result);
+        let fired = if explain { fired() } else { Vec::new() };
+        stages.push(NameStage { stage, result: result.clone(), fired });
+        result
     };
 
-    // Remove Roman numbers
-    result = string_remove_roman_number(&result).unwrap(); 
+    let mut result = record("filtered", string_remove_filtered(folder_name, filter_words).unwrap(), &|| find_filtered(folder_name, filter_words));
 
-    debug!("After removing roman numbers: {}", &result);
+    // Years and the spaces left behind
+    result = record("years", string_remove_years(&result).unwrap(), &|| {
+        let mut fired = find_years(&result);
+        fired.append(&mut find_fired(DUPLICATE_SPACES_PATTERN, &string_replace_years(&result)));
+        fired
+    });
+    result = record("roman_number", string_remove_roman_number(&result).unwrap(), &|| find_fired(ROMAN_NUMBER_PATTERN, &result));
 
-    Ok(result.trim().to_string())
+    // Episode ranges and the brackets left empty
+    result = record("episode_range", string_remove_episode_range(&result).unwrap(), &|| {
+        let mut fired = find_fired(EPISODE_RANGE_PATTERN, &result);
+        fired.append(&mut find_fired(EMPTY_BRACKETS_PATTERN, &EPISODE_RANGE_REGEX.replace_all(&result, " ")));
+        fired
+    });
+    result = record("empty_brackets", string_remove_empty_brackets(&result).unwrap(), &|| find_fired(EMPTY_BRACKETS_PATTERN, &result));
+    result = record("duplicate_spaces", string_remove_duplicate_spaces(&result).unwrap(), &|| find_fired(DUPLICATE_SPACES_PATTERN, &result));
+
+    // Roman numbers only trailing once brackets are gone
+    result = record("roman_number", string_remove_roman_number(&result).unwrap(), &|| find_fired(ROMAN_NUMBER_PATTERN, &result));
+
+    // Explicit season number
+    record("season_number", string_remove_season_number(&result).unwrap(), &|| find_season_markers(&result));
+
+    stages
 }
 
-pub fn extract_series_season_number(file_name: &str, filter_words: &FilterWords) -> Result<u16, ()> { // TODO: Move this function to struct
+/// Matches of a regex which change the name when replaced by a space
+fn find_fired(pattern: &str, input: &str) -> Vec<StageMatch> {
+    Regex::new(pattern).unwrap().find_iter(input)
+        .filter(|i| i.as_str() != " ")
+        .map(|i| StageMatch { pattern: pattern.to_string(), text: i.as_str().to_string() })
+        .collect()
+}
+
+/// Years removed by string_remove_years
+fn find_years(input: &str) -> Vec<StageMatch> {
+    YEAR_REGEX.captures_iter(input)
+        .filter(|caps| caps[1].parse::<i32>().is_ok_and(|year| (1928..=2030).contains(&year)))
+        .map(|caps| StageMatch { pattern: YEAR_PATTERN.to_string(), text: caps[0].to_string() })
+        .collect()
+}
+
+/// Season markers found in a name, every regex runs on the result of the previous one
+fn find_season_markers(input: &str) -> Vec<StageMatch> {
+    let mut result = Vec::new();
+    let mut middleware = input.to_string();
+    for pattern in season_number_patterns() {
+        result.append(&mut find_fired(&pattern, &middleware));
+        middleware = Regex::new(&pattern).unwrap().replace_all(&middleware, " ").trim().to_string();
+    }
+    result
+}
+
+/// CC names and Meta tags found in a name
+fn find_filtered(input: &str, filter_words: &FilterWords) -> Vec<StageMatch> {
+    filter_words.lists.cc_group.iter()
+        .chain(filter_words.lists.meta_tag.iter())
+        .flat_map(|word| find_fired(&format!("(?i){}", word.pattern()), input)
+            .into_iter()
+            .map(|i| StageMatch { pattern: word.to_string(), ..i }))
+        .collect()
+}
+
+static SERIES_YEAR_REGEXES: LazyLock<[Regex; 3]> = LazyLock::new(|| {
+    [
+        r"[\[(（]\s*((?:19|20)[0-9]{2})\s*[\])）]",
+        r"(?:^|[^0-9])((?:19|20)[0-9]{2})\.(?:0?[1-9]|1[0-2])(?:[^0-9]|$)",
+        r"(?:^|[\s._])((?:19|20)[0-9]{2})(?:[\s._]|$)",
+    ].map(|i| Regex::new(i).unwrap())
+});
+
+/// Find the release year in a folder name, like (2019), [2001] or 2023.10
+pub fn extract_series_year(folder_name: &str, filter_words: &FilterWords) -> Option<u16> {
     // Test covered
-    let clean_file_name = string_remove_square_brackets(&string_remove_filtered(&file_name).unwrap()).unwrap().trim().to_string();
+    let clean_folder_name = string_remove_filtered(&string_remove_crc(folder_name), filter_words).unwrap();
+
+    // Bracketed years are the most reliable, then year with month, then a number standing alone
+    for reg in SERIES_YEAR_REGEXES.iter() {
+        for caps in reg.captures_iter(&clean_folder_name) {
+            let year = match caps[1].parse::<u16>() {
+                Ok(year) => year,
+                Err(_) => continue,
+            };
+            if (1928..=2030).contains(&year) {
+                debug!("Successfully extract year {} from {}", &year, &folder_name);
+                return Some(year);
+            }
+        }
+    }
+
+    debug!("Fail to find year in {}", &folder_name);
+    None
+}
+
+/// How a second cour released as "Part 2" or "Cour 2" is numbered
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitCour {
+    /// Default, a cour continues its season, like most episode databases list it
+    #[default]
+    Merge,
+    /// Every cour is a season of its own
+    Separate,
+}
+
+impl fmt::Display for SplitCour {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SplitCour::Merge => "merge",
+            SplitCour::Separate => "separate",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for SplitCour {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.trim().to_lowercase().as_str() {
+            "merge" => Ok(SplitCour::Merge),
+            "separate" => Ok(SplitCour::Separate),
+            _ => {
+                warn!("Unknown split cour policy {}", &input);
+                Err(())
+            }
+        }
+    }
+}
+
+/// English ordinal words used in names like "Second Season"
+const ORDINAL_WORDS: [&str; 10] = ["first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth", "tenth"];
+
+/// Japanese sequel markers ending a name and the season they usually stand for, like Oregairu Zoku and Oregairu Kan
+const SEQUEL_MARKERS: [(&str, u16); 3] = [
+    ("Zoku", 2),
+    ("Shin", 2),
+    ("Kan", 3),
+];
+
+/// Sequel markers starting a name, like Zoku Natsume Yuujinchou. Shin is left out, it starts plenty of first seasons
+const SEQUEL_PREFIXES: [(&str, u16); 1] = [
+    ("Zoku", 2),
+];
+
+static ROMAN_NUMERAL_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)^(I{1,3}|IV|VI{0,3}|IX|XI{0,3})$").unwrap());
+
+/// Parse a number written in digits, Roman numerals, or Chinese numerals up to 99
+fn string_parse_number(input: &str) -> Option<u16> {
+    if let Ok(number) = input.parse::<u16>() {
+        return Some(number);
+    }
+    if ROMAN_NUMERAL_REGEX.is_match(input) {
+        return roman_to_int(&input.to_uppercase()).ok();
+    }
+
+    const CHINESE_DIGITS: [char; 10] = ['零', '一', '二', '三', '四', '五', '六', '七', '八', '九'];
+    let digit = |c: char| CHINESE_DIGITS.iter().position(|i| *i == c).map(|i| i as u16);
+    let chars: Vec<char> = input.chars().collect();
+    match chars.as_slice() {
+        [c] if *c == '十' => Some(10),
+        [c] => digit(*c),
+        ['十', c] => digit(*c).map(|i| 10 + i),
+        [c, '十'] => digit(*c).map(|i| i * 10),
+        [a, '十', b] => Some(digit(*a)? * 10 + digit(*b)?),
+        _ => None,
+    }
+}
+
+static FINAL_SEASON_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)\bfinal\s+season\b").unwrap());
+
+pub fn extract_series_season_number(file_name: &str, filter_words: &FilterWords, split_cour: SplitCour) -> Result<u16, ()> { // TODO: Move this function to struct
+    // Test covered
+    match find_season_number(file_name, filter_words, split_cour) {
+        Some(season_number) => Ok(season_number),
+        // The real number is only known to episode databases
+        None if FINAL_SEASON_REGEX.is_match(file_name) => {
+            warn!("{} is a final season without season number, set its season in overrides.", &file_name);
+            Err(())
+        },
+        None => Ok(1),
+    }
+}
+
+static FILE_SEASON_MARKER_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)(?:^|[^[:alnum:]])S(\d{1,2})(?:E\d{1,4}|\s*-\s*\d{1,4})").unwrap());
+
+/// Find the season of a file in a batch, from a season folder like "S2" or "Show II", or a marker like S01E05 or "S2 - 05"
+fn extract_file_season_number(file_name: &str, filter_words: &FilterWords, split_cour: SplitCour) -> Option<u16> {
+    // Test covered
+    let path = Path::new(file_name);
+    if let Some(parent) = path.parent() {
+        for component in parent.iter() {
+            if let Some(season_number) = find_season_number(&component.to_string_lossy(), filter_words, split_cour) {
+                debug!("Found season {} of {} by folder", &season_number, &file_name);
+                return Some(season_number);
+            }
+        }
+    }
+
+    let name = string_get_file_name(file_name);
+    let caps = FILE_SEASON_MARKER_REGEX.captures(&name)?;
+    let season_number = caps[1].parse::<u16>().ok()?;
+    debug!("Found season {} of {} by file name", &season_number, &file_name);
+    Some(season_number)
+}
+
+static COUR_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)\s*\b(?:part|cour)\s*(\d{1,2}|I{1,3}|IV|VI{0,3})\b").unwrap());
+static SEASON_ROMAN_NUMERAL_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)\s+(I{1,3}|IV|VI{0,3}|IX|XI{0,3})$").unwrap());
+static EXPLICIT_SEASON_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)(?:^|[\s._])(?:season|S)\s*(\d{1,2})(?:$|[\s._E])").unwrap());
+static CJK_SEASON_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"第\s*([\d一二三四五六七八九十]+)\s*[季期部]|([一二三四五六七八九十]+)期").unwrap());
+static ORDINAL_SEASON_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(&format!(r"(?i)\b(\d{{1,2}})(?:st|nd|rd|th)\s+season\b|\b({})\s+season\b", ORDINAL_WORDS.join("|"))).unwrap());
+static SEQUEL_MARKER_REGEXES: LazyLock<Vec<(Regex, &'static str, u16)>> = LazyLock::new(|| {
+    SEQUEL_MARKERS.iter().map(|(marker, season_number)| (format!(r"(?i)\s{}$", marker), *marker, *season_number))
+        .chain(SEQUEL_PREFIXES.iter().map(|(marker, season_number)| (format!(r"(?i)^{}\s", marker), *marker, *season_number)))
+        .map(|(reg_str, marker, season_number)| (Regex::new(&reg_str).unwrap(), marker, season_number))
+        .collect()
+});
+
+/// Find a season number marked in a folder or file name, None when nothing marks it
+fn find_season_number(file_name: &str, filter_words: &FilterWords, split_cour: SplitCour) -> Option<u16> {
+    let mut clean_file_name = string_remove_square_brackets(&string_remove_filtered(file_name, filter_words).unwrap()).unwrap().trim().to_string();
+
+    // Split cour, kept aside so "Part II" is not taken for a Roman season number
+    let mut cour_number = None;
+    {
+        let reg = &*COUR_REGEX;
+        if let Some(caps) = reg.captures(&clean_file_name) {
+            cour_number = string_parse_number(&caps[1]);
+            debug!("Found split cour {:?}", &cour_number);
+            clean_file_name = reg.replace(&clean_file_name, " ").trim().to_string();
+        }
+    }
 
     // Extract from Roman numerals
     {
-        let reg = Regex::new(r"(?i)\s+(I{1,3}|IV|VI{0,3}|IX|XI{0,3})$").unwrap();
+        let reg = &*SEASON_ROMAN_NUMERAL_REGEX;
         match reg.captures(&clean_file_name) {
-            Some(caps) => match roman_to_int(&caps[1]) {
+            Some(caps) => match roman_to_int(&caps[1].to_uppercase()) {
                 Ok(season_number) => {
                     debug!("Successfully extract season number from Roman numeral, {}", &season_number);
-                    return Ok(season_number);
+                    return Some(season_number);
                 },
                 Err(_) => debug!("Fail to infer season number from Roman numeral."),
             },
@@ -166,14 +1443,14 @@ pub fn extract_series_season_number(file_name: &str, filter_words: &FilterWords)
         }
     }
 
-    // Extract from explicit season number
+    // Extract from explicit season number, like Season 3, S2 or S02
     {
-        let reg = Regex::new(r"(?i)\s+(?:season|S)\s*(\d+)$").unwrap();
+        let reg = &*EXPLICIT_SEASON_REGEX;
         match reg.captures(&clean_file_name) {
             Some(caps) => match &caps[1].parse::<u16>() {
                 Ok(season_number) => {
                     debug!("Successfully extract season number from explicit season number, {}", &season_number);
-                    return Ok(*season_number);
+                    return Some(*season_number);
                 },
                 Err(_) => debug!("Fail to infer season number from explicit season number."),
             },
@@ -181,100 +1458,539 @@ pub fn extract_series_season_number(file_name: &str, filter_words: &FilterWords)
         }
     }
 
-    // TODO: Non-explicit season number
-    Ok(1)
+    // Extract from ordinal season, like 2nd Season or Second Season
+    {
+        if let Some(caps) = ORDINAL_SEASON_REGEX.captures(&clean_file_name) {
+            let season_number = match (caps.get(1), caps.get(2)) {
+                (Some(number), _) => number.as_str().parse::<u16>().ok(),
+                (_, Some(word)) => ORDINAL_WORDS.iter().position(|i| i.eq_ignore_ascii_case(word.as_str())).map(|i| i as u16 + 1),
+                _ => None,
+            };
+            if let Some(season_number) = season_number {
+                debug!("Successfully extract season number from ordinal season, {}", &season_number);
+                return Some(season_number);
+            }
+        }
+    }
+
+    // Extract from CJK season, like 第二季, 第2期 or 二期
+    {
+        let reg = &*CJK_SEASON_REGEX;
+        if let Some(caps) = reg.captures(&clean_file_name) {
+            let number = caps.get(1).or(caps.get(2)).map_or("", |i| i.as_str());
+            if let Some(season_number) = string_parse_number(number) {
+                debug!("Successfully extract season number from CJK season, {}", &season_number);
+                return Some(season_number);
+            }
+        }
+    }
+
+    // Each cour may count as a season of its own
+    if let (Some(cour_number), SplitCour::Separate) = (cour_number, split_cour) {
+        debug!("Successfully extract season number from split cour, {}", &cour_number);
+        return Some(cour_number);
+    }
+
+    // Sequel markers without a number
+    for (reg, marker, season_number) in SEQUEL_MARKER_REGEXES.iter() {
+        if reg.is_match(&clean_file_name) {
+            debug!("Successfully extract season number from sequel marker {}, {}", &marker, &season_number);
+            return Some(*season_number);
+        }
+    }
+
+    None
 }
 
-enum EpisodeType {
-    Main(u16),
-    Extra(u16)    
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EpisodeType {
+    #[default]
+    Main,
+    /// SP, OVA, TV specials, numbered in Season 00
+    Special,
+    /// Creditless opening
+    Opening,
+    /// Creditless ending
+    Ending,
+    /// PV, CM, trailer and preview
+    Trailer,
+    Menu,
+    /// Anything else shipped along, making of, scans, fonts
+    Bonus,
 }
 
-fn extract_episode_type() {
-    todo!()
+impl EpisodeType {
+    /// Order of keyword lookup, the first match wins
+    const ORDER: [EpisodeType; 7] = [
+        EpisodeType::Opening,
+        EpisodeType::Ending,
+        EpisodeType::Trailer,
+        EpisodeType::Menu,
+        EpisodeType::Special,
+        EpisodeType::Bonus,
+        EpisodeType::Main,
+    ];
 }
 
-pub fn extract_episode_number(file_names: Vec<String>) -> Option<Vec<Episode>> {
-    // This function deals with a list of file names (0 to inf), build context to find the unique numbers in the list of files,
-    // and return a list of files with their episode number.
-    let file_names_length = file_names.len() as u16; // TODO: make it safer
-    match file_names.len() {
-        0 => {
-            return None;
-        },
-        1 => {
-            // Extract episode number without context
-            // Make the assumption that the correct one is the first one
-            // TODO: Improve algorithm
-            match string_find_episode_number(&file_names[0]) {
-                Ok(ep_number) => {
-                    debug!("Find episode number {}", &ep_number[0]);
-                    return Some(vec![Episode{sequence: ep_number[0], ..Default::default() }]);
-                },
-                Err(_) => {
-                    warn!("Failed to find episode number.");
-                    return None
-                }
+static SPECIAL_FOLDER_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)\s(?:OVA|OAD|TVSP|SPs?|Specials?)s?$").unwrap());
+
+/// Classify a whole series folder by a trailing word, like "Show OVA" or "Show Specials"
+fn extract_folder_episode_type(folder_name: &str, filter_words: &FilterWords) -> EpisodeType {
+    let clean_folder_name = string_remove_duplicate_spaces(&string_remove_filtered(folder_name, filter_words).unwrap()).unwrap();
+    match SPECIAL_FOLDER_REGEX.is_match(&clean_folder_name) {
+        true => EpisodeType::Special,
+        false => EpisodeType::Main,
+    }
+}
+
+/// Classify file by low priority words in its name and parent folders
+fn extract_episode_type(file_name: &str, filter_words: &FilterWords) -> EpisodeType {
+    // Test covered
+    for episode_type in EpisodeType::ORDER {
+        let finders = match filter_words.low_priority_finders.get(&episode_type) {
+            Some(finders) => finders,
+            None => continue,
+        };
+        for (word, finder) in finders {
+            if finder.is_match(file_name) {
+                debug!("Classified {} as {:?} by {}", &file_name, &episode_type, &word);
+                return episode_type;
             }
-        },
-        _ => {
-            // Extract episode number with context
-
-            // Extract context in the first pass
-            let mut context = HashMap::<u16, u16>::new();
-            for file_name in &file_names {
-                match string_find_episode_number(&file_name) {
-                    Ok(candidates) => {
-                        for candidate in candidates {
-                            context.entry(candidate).and_modify(|counter| *counter += 1).or_insert(1);
-                        }
-                    },
-                    Err(_) => {
-                        warn!("Failed to extract episode number from file name {}", &file_name);
-                        continue;
-                    }
-                };
+        }
+    }
+
+    EpisodeType::Main
+}
+
+/// Number every file among files of the same kind, one result per file in the same order.
+/// Numbers shared by many files, like a resolution or season, are unlikely to be the episode number.
+pub fn extract_episode_number(file_names: Vec<String>, filter_words: &FilterWords) -> Vec<EpisodeNumber> {
+    // Test covered
+    let clean_names: Vec<String> = file_names.iter()
+        .map(|file_name| string_clean_episode_name(file_name, filter_words))
+        .collect();
+    let candidates_list: Vec<Vec<u16>> = clean_names.iter()
+        .map(|clean_name| string_find_episode_number(clean_name).unwrap_or_default())
+        .collect();
+
+    // Count files containing each number
+    let mut context = HashMap::<u16, u16>::new();
+    for candidates in &candidates_list {
+        for candidate in candidates {
+            context.entry(*candidate).and_modify(|counter| *counter += 1).or_insert(1);
+        }
+    }
+
+    let mut result = Vec::new();
+    for ((file_name, clean_name), candidates) in file_names.iter().zip(&clean_names).zip(candidates_list) {
+        if candidates.is_empty() {
+            warn!("Failed to find any episode number in {}", &file_name);
+            result.push(EpisodeNumber::Unresolved(file_name.clone()));
+            continue;
+        }
+
+        // Document frequency of every candidate, stable sort keeps the earliest among ties
+        let mut scoring: Vec<EpisodeCandidate> = candidates.iter()
+            .map(|candidate| EpisodeCandidate {
+                number: *candidate,
+                score: context[candidate] as f32 / file_names.len() as f32,
+            })
+            .collect();
+        scoring.sort_by(|a, b| a.score.partial_cmp(&b.score).unwrap());
+
+        let best = &scoring[0];
+        let (rule, confidence) = match (scoring.get(1), file_names.len()) {
+            (None, _) => (EpisodeNumberRule::OnlyCandidate, 1.0),
+            (Some(_), 1) => (EpisodeNumberRule::FirstCandidate, 1.0 / scoring.len() as f32),
+            // How decisively the chosen candidate beats the runner-up
+            (Some(runner_up), _) => (EpisodeNumberRule::LeastFrequent, 1.0 - best.score / runner_up.score),
+        };
+        let reason = match rule {
+            EpisodeNumberRule::OnlyCandidate => "Only episode number candidate".to_string(),
+            EpisodeNumberRule::FirstCandidate => format!("First of {} episode number candidates without context", scoring.len()),
+            EpisodeNumberRule::LeastFrequent | EpisodeNumberRule::SequenceRun => format!("Least frequent of {} episode number candidates among {} files", scoring.len(), file_names.len()),
+        };
+        debug!("Find episode number {} in {} by {}, confidence {:.2}", &best.number, &file_name, &rule, &confidence);
+
+        result.push(EpisodeNumber::Resolved(Box::new(Episode {
+            sequence: best.number,
+            last_sequence: string_find_episode_range(clean_name)
+                .filter(|(first, _)| *first == best.number)
+                .map(|(_, last)| last),
+            location: file_name.clone(),
+            version: string_find_episode_version(clean_name),
+            finale: string_is_finale(clean_name),
+            confidence,
+            reason,
+            rule: Some(rule),
+            candidates: scoring,
+            ..Default::default()
+        })));
+    }
+
+    // Files sorted by name usually count up, a weak pick breaking the run gives way to the candidate continuing it
+    if file_names.len() > 1 {
+        let mut order: Vec<usize> = (0..file_names.len()).collect();
+        order.sort_by_key(|index| &file_names[*index]);
+        let mut previous = 0;
+        for index in order {
+            let episode = match &mut result[index] {
+                EpisodeNumber::Resolved(episode) => episode,
+                EpisodeNumber::Unresolved(_) => continue,
+            };
+            let next = previous + 1;
+            if episode.confidence < REVIEW_CONFIDENCE && episode.sequence != next && episode.candidates.iter().any(|candidate| candidate.number == next) {
+                debug!("Episode {} continues the run in {}, over {}", &next, &episode.location, &episode.sequence);
+                episode.reason = format!("{}, episode {} preferred over {} as it continues the run after {}", &episode.reason, &next, &episode.sequence, &previous);
+                episode.sequence = next;
+                episode.last_sequence = string_find_episode_range(&clean_names[index])
+                    .filter(|(first, _)| *first == next)
+                    .map(|(_, last)| last);
+                episode.rule = Some(EpisodeNumberRule::SequenceRun);
             }
+            previous = episode.last_sequence.unwrap_or(episode.sequence);
+        }
+    }
 
-            // Indexing context using Inverted document frequency
-            let mut indexed_context = HashMap::<u16, f32>::new();
-            let total_document_count: u16 = file_names_length;
-            for (key, frequency) in context.iter() {
-                let inverted_document_frequency = frequency.clone() as f32 / total_document_count.clone() as f32;
-                indexed_context.insert(key.clone(), inverted_document_frequency);
-            }
-            
-            // Find episode number based on reg and context
-            let mut result: Vec<Episode> = Vec::new();
-            for file_name in &file_names {
-                match string_find_episode_number(&file_name) {
-                    Ok(candidates) => {
-                        let mut scoring: Vec<(f32, u16)> = Vec::new(); // Storing file_name related episode_number in new vector
-                        for candidate in candidates {
-                            let score = match indexed_context.get(&candidate) {
-                                Some(sth) => *sth,
-                                None => {
-                                    error!("Inconsistent find episode number function.");
-                                    continue
-                                }
-                            };
-                            scoring.push((score, candidate));
-                        }
-                        scoring.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
-                        result.push(Episode{sequence: scoring[0].1,..Default::default()})
-                    },
-                    Err(_) => {
-                        warn!("Failed to find any  episode number in {}", &file_name);
-                        continue
-                    },
-                }
+    result
+}
+
+/// Everything extracted from a single file or folder name, taken apart on its own without the other files
+#[derive(Debug, Serialize)]
+pub struct NameExplanation {
+    pub name: String,
+    pub series_name: Option<String>,
+    pub year: Option<u16>,
+    /// Season of a folder named like this
+    pub season: u16,
+    /// Season of a file named like this, from its path or an S01E05 marker
+    pub file_season: Option<u16>,
+    pub episode_type: EpisodeType,
+    /// Episode number candidates, the first wins without other files to compare
+    pub episode_numbers: Vec<u16>,
+    pub episode_range: Option<(u16, u16)>,
+    pub decimal_episode: Option<String>,
+    pub version: u8,
+    pub finale: bool,
+    pub release: ReleaseInfo,
+    /// Name after every cleaning stage of series name extraction
+    pub series_name_stages: Vec<NameStage>,
+}
+
+/// Take a file or folder name apart with every extraction the organizer runs on names
+pub fn explain_name(name: &str, filter_words: &FilterWords, file_extensions: &FileExtensions, split_cour: SplitCour) -> NameExplanation {
+    // Folder extractions only look at the last path component, without extension for files
+    let file_name = match extract_file_extension(name, file_extensions) {
+        Some(_) => string_remove_file_extension(&string_get_file_name(name)),
+        None => string_get_file_name(name),
+    };
+    let clean_name = string_clean_episode_name(name, filter_words);
+    NameExplanation {
+        name: name.to_string(),
+        series_name: extract_series_name(&file_name, filter_words).ok(),
+        year: extract_series_year(&file_name, filter_words),
+        season: extract_series_season_number(&file_name, filter_words, split_cour).unwrap_or(1),
+        file_season: extract_file_season_number(name, filter_words, split_cour),
+        episode_type: extract_episode_type(name, filter_words),
+        episode_numbers: string_find_episode_number(&clean_name).unwrap_or_default(),
+        episode_range: string_find_episode_range(&clean_name),
+        decimal_episode: string_find_decimal_episode(&clean_name),
+        version: string_find_episode_version(&clean_name),
+        finale: string_is_finale(&clean_name),
+        release: extract_release_info(&file_name, filter_words),
+        series_name_stages: trace_series_name(&file_name, filter_words),
+    }
+}
+
+/// Normalized language tags by lower case token found in subtitle names
+const SUBTITLE_LANGUAGES: [(&str, &str); 28] = [
+    ("sc", "zh-Hans"),
+    ("chs", "zh-Hans"),
+    ("gb", "zh-Hans"),
+    ("zh-hans", "zh-Hans"),
+    ("zh-cn", "zh-Hans"),
+    ("zh_cn", "zh-Hans"),
+    ("chi_sim", "zh-Hans"),
+    ("tc", "zh-Hant"),
+    ("cht", "zh-Hant"),
+    ("big5", "zh-Hant"),
+    ("zh-hant", "zh-Hant"),
+    ("zh-tw", "zh-Hant"),
+    ("zh_tw", "zh-Hant"),
+    ("chi_tra", "zh-Hant"),
+    ("zh", "zh"),
+    ("chi", "zh"),
+    ("jp", "ja"),
+    ("jpn", "ja"),
+    ("ja", "ja"),
+    ("japanese", "ja"),
+    ("en", "en"),
+    ("eng", "en"),
+    ("english", "en"),
+    ("ko", "ko"),
+    ("kor", "ko"),
+    ("kr", "ko"),
+    ("ru", "ru"),
+    ("rus", "ru"),
+];
+
+static LANGUAGE_TOKEN_SEPARATOR_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[.\[\]()\s&+,]").unwrap());
+
+/// Find a subtitle language in a name suffix, normalized to ISO 639 with script for Chinese.
+/// Bilingual subtitles like JPSC or CHS_JP resolve to the translated language.
+fn extract_subtitle_language(input: &str) -> Option<String> {
+    // Test covered
+    let mut languages = Vec::<String>::new();
+    for token in LANGUAGE_TOKEN_SEPARATOR_REGEX.split(input) {
+        let token = token.to_lowercase();
+        if token.is_empty() {
+            continue;
+        }
+
+        // Chinese characters, like 简日 or 繁体
+        if token.contains('简') {
+            languages.push("zh-Hans".to_string());
+        }
+        if token.contains('繁') {
+            languages.push("zh-Hant".to_string());
+        }
+        if token.contains('日') {
+            languages.push("ja".to_string());
+        }
+
+        // Whole token, or tokens joined together like jptc, chs_jp or scjp
+        let parts: Vec<String> = match SUBTITLE_LANGUAGES.iter().any(|(name, _)| *name == token) {
+            true => vec![token],
+            false => token.split(['_', '-']).map(|part| part.to_string()).collect(),
+        };
+        for part in parts {
+            languages.extend(string_split_languages(&part));
+        }
+    }
+    debug!("Languages in {}: {:?}", &input, &languages);
+
+    // Japanese is usually the original audio language in bilingual subtitles
+    languages.iter().find(|language| *language != "ja").or(languages.first()).cloned()
+}
+
+/// Split a token made of language names joined together, like jptc into ja and zh-Hant
+fn string_split_languages(token: &str) -> Vec<String> {
+    if let Some((_, language)) = SUBTITLE_LANGUAGES.iter().find(|(name, _)| *name == token) {
+        return vec![language.to_string()];
+    }
+    for (name, language) in SUBTITLE_LANGUAGES.iter().filter(|(name, _)| name.len() > 1) {
+        if let Some(rest) = token.strip_prefix(name) {
+            let rest = string_split_languages(rest);
+            if !rest.is_empty() {
+                return [vec![language.to_string()], rest].concat();
             }
+        }
+    }
+    Vec::new()
+}
+
+/// Release details carried by fansub folder and file names
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReleaseInfo {
+    /// Fansub and raw groups, collaborations like DMG&SumiSora&VCB-Studio are split
+    pub groups: Vec<String>,
+    pub resolution: Option<String>,
+    pub video_codec: Option<String>,
+    pub bit_depth: Option<u8>,
+    pub audio_codecs: Vec<String>,
+    /// Where the video was ripped from, like BDRip or WEB-DL
+    pub source: Option<String>,
+    /// Streaming platform of web releases, like Baha or NF
+    pub platform: Option<String>,
+    pub hdr: bool,
+}
+
+impl ReleaseInfo {
+    /// Fill in missing details from another release, like the series folder
+    fn merge(&self, fallback: &ReleaseInfo) -> ReleaseInfo {
+        ReleaseInfo {
+            groups: if self.groups.is_empty() { fallback.groups.clone() } else { self.groups.clone() },
+            resolution: self.resolution.clone().or(fallback.resolution.clone()),
+            video_codec: self.video_codec.clone().or(fallback.video_codec.clone()),
+            bit_depth: self.bit_depth.or(fallback.bit_depth),
+            audio_codecs: if self.audio_codecs.is_empty() { fallback.audio_codecs.clone() } else { self.audio_codecs.clone() },
+            source: self.source.clone().or(fallback.source.clone()),
+            platform: self.platform.clone().or(fallback.platform.clone()),
+            hdr: self.hdr || fallback.hdr,
+        }
+    }
+}
 
-            return Some(result);
+impl fmt::Display for ReleaseInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::<String>::new();
+        if !self.groups.is_empty() {
+            parts.push(format!("[{}]", self.groups.join("&")));
+        }
+        parts.extend(self.source.clone());
+        parts.extend(self.platform.clone());
+        parts.extend(self.resolution.clone());
+        parts.extend(self.video_codec.clone());
+        parts.extend(self.bit_depth.map(|bit_depth| format!("{}bit", bit_depth)));
+        parts.extend(self.audio_codecs.clone());
+        if self.hdr {
+            parts.push("HDR".to_string());
         }
+        write!(f, "{}", parts.join(" "))
     }
 }
 
+/// Normalized resolution by tag, the first match wins
+const RESOLUTION_TAGS: [(&str, &str); 11] = [
+    ("2160p", "2160p"),
+    ("3840x2160", "2160p"),
+    ("4K", "2160p"),
+    ("1440p", "1440p"),
+    ("2K", "1440p"),
+    ("1080p", "1080p"),
+    ("1920x1080", "1080p"),
+    ("720p", "720p"),
+    ("1280x720", "720p"),
+    ("576p", "576p"),
+    ("480p", "480p"),
+];
+
+/// Normalized video codec by tag
+const VIDEO_CODEC_TAGS: [(&str, &str); 11] = [
+    ("HEVC", "HEVC"),
+    ("x265", "HEVC"),
+    ("H265", "HEVC"),
+    ("H.265", "HEVC"),
+    ("AVC", "AVC"),
+    ("x264", "AVC"),
+    ("x.264", "AVC"),
+    ("H264", "AVC"),
+    ("H.264", "AVC"),
+    ("AV1", "AV1"),
+    ("VP9", "VP9"),
+];
+
+/// Bit depth by tag, Ma10p and YUV420P10 are 10 bit encodes
+const BIT_DEPTH_TAGS: [(&str, u8); 7] = [
+    ("10bit", 10),
+    ("10-bit", 10),
+    ("Ma10p", 10),
+    ("Hi10p", 10),
+    ("YUV420P10", 10),
+    ("8bit", 8),
+    ("8-bit", 8),
+];
+
+/// Normalized audio codec by tag, a release may carry several
+const AUDIO_CODEC_TAGS: [(&str, &str); 10] = [
+    ("FLAC", "FLAC"),
+    ("AAC", "AAC"),
+    ("EAC3", "EAC3"),
+    ("DDP", "EAC3"),
+    ("AC3", "AC3"),
+    ("DTS", "DTS"),
+    ("TrueHD", "TrueHD"),
+    ("Opus", "Opus"),
+    ("LPCM", "LPCM"),
+    ("MP3", "MP3"),
+];
+
+/// Normalized source by tag, the first match wins
+const SOURCE_TAGS: [(&str, &str); 11] = [
+    ("BDRip", "BDRip"),
+    ("BluRay", "BDRip"),
+    ("Blu-ray", "BDRip"),
+    ("BD", "BDRip"),
+    ("WEB-DL", "WEB-DL"),
+    ("WEBDL", "WEB-DL"),
+    ("WebRip", "WEBRip"),
+    ("HDTV", "HDTV"),
+    ("TVRip", "HDTV"),
+    ("DVDRip", "DVDRip"),
+    ("DVD", "DVDRip"),
+];
+
+/// Normalized streaming platform by tag
+const PLATFORM_TAGS: [(&str, &str); 11] = [
+    ("Baha", "Baha"),
+    ("NF", "NF"),
+    ("Netflix", "NF"),
+    ("DSNP", "DSNP"),
+    ("Disney+", "DSNP"),
+    ("AMZN", "AMZN"),
+    ("CR", "CR"),
+    ("Crunchyroll", "CR"),
+    ("ABEMA", "ABEMA"),
+    ("B-Global", "B-Global"),
+    ("Bilibili", "B-Global"),
+];
+
+const HDR_TAGS: [&str; 4] = ["HDR", "HDR10", "DoVi", "Dolby Vision"];
+
+/// Whole word matchers of every tag in a table, matches come in table order
+fn tag_set<'a>(tags: impl Iterator<Item = &'a str>) -> RegexSet {
+    RegexSet::new(tags.map(word_pattern)).unwrap()
+}
+
+static RESOLUTION_TAG_SET: LazyLock<RegexSet> = LazyLock::new(|| tag_set(RESOLUTION_TAGS.iter().map(|(tag, _)| *tag)));
+static VIDEO_CODEC_TAG_SET: LazyLock<RegexSet> = LazyLock::new(|| tag_set(VIDEO_CODEC_TAGS.iter().map(|(tag, _)| *tag)));
+static BIT_DEPTH_TAG_SET: LazyLock<RegexSet> = LazyLock::new(|| tag_set(BIT_DEPTH_TAGS.iter().map(|(tag, _)| *tag)));
+static AUDIO_CODEC_TAG_SET: LazyLock<RegexSet> = LazyLock::new(|| tag_set(AUDIO_CODEC_TAGS.iter().map(|(tag, _)| *tag)));
+static SOURCE_TAG_SET: LazyLock<RegexSet> = LazyLock::new(|| tag_set(SOURCE_TAGS.iter().map(|(tag, _)| *tag)));
+static PLATFORM_TAG_SET: LazyLock<RegexSet> = LazyLock::new(|| tag_set(PLATFORM_TAGS.iter().map(|(tag, _)| *tag)));
+static HDR_TAG_SET: LazyLock<RegexSet> = LazyLock::new(|| tag_set(HDR_TAGS.iter().copied()));
+static LEADING_BRACKET_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*[\[【]([^\]】]+)[\]】]").unwrap());
+static NUMBERS_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[\d\s-]+$").unwrap());
+
+/// Collect release details from a folder or file name, tags are matched like low priority words
+fn extract_release_info(file_name: &str, filter_words: &FilterWords) -> ReleaseInfo {
+    // Test covered
+    // First tag of the table found in the name
+    let find = |tags: &[(&str, &str)], tag_set: &RegexSet| tag_set.matches(file_name).iter()
+        .next()
+        .map(|index| tags[index].1.to_string());
+
+    let mut result = ReleaseInfo {
+        resolution: find(&RESOLUTION_TAGS, &RESOLUTION_TAG_SET),
+        video_codec: find(&VIDEO_CODEC_TAGS, &VIDEO_CODEC_TAG_SET),
+        bit_depth: BIT_DEPTH_TAG_SET.matches(file_name).iter().next().map(|index| BIT_DEPTH_TAGS[index].1),
+        source: find(&SOURCE_TAGS, &SOURCE_TAG_SET),
+        platform: find(&PLATFORM_TAGS, &PLATFORM_TAG_SET),
+        hdr: HDR_TAG_SET.is_match(file_name),
+        ..Default::default()
+    };
+    for index in AUDIO_CODEC_TAG_SET.matches(file_name).iter() {
+        let normalized = AUDIO_CODEC_TAGS[index].1;
+        if !result.audio_codecs.iter().any(|i| i == normalized) {
+            result.audio_codecs.push(normalized.to_string());
+        }
+    }
+
+    // Leading bracket names the releasing groups, even ones not listed in filter words
+    if let Some(caps) = LEADING_BRACKET_REGEX.captures(file_name) {
+        for group in caps[1].split('&').map(|group| group.trim()) {
+            let is_tag = group.is_empty()
+                || NUMBERS_REGEX.is_match(group)
+                || RESOLUTION_TAGS.iter().chain(PLATFORM_TAGS.iter()).any(|(tag, _)| tag.eq_ignore_ascii_case(group));
+            if !is_tag {
+                result.groups.push(group.to_string());
+            }
+        }
+    }
+    for group in filter_words.cc_group_finders.iter().filter_map(|finder| Some(finder.captures(file_name)?[1].to_string())) {
+        let is_known = result.groups.iter().any(|i| i.eq_ignore_ascii_case(&group));
+        let is_platform = PLATFORM_TAGS.iter().any(|(tag, _)| tag.eq_ignore_ascii_case(&group));
+        if !is_known && !is_platform {
+            result.groups.push(group);
+        }
+    }
+
+    debug!("Release info of {}: {:?}", &file_name, &result);
+    result
+}
+
 // Extract helper
 
 /// Convert roman numeral to integer
@@ -309,34 +2025,46 @@ fn roman_to_int(roman: &str) -> Result<u16, ()> {
     Ok(result.try_into().unwrap())
 }
 
-enum FileExtensionNames {
-
+/// Kind of file, decided by extension name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileExtensionNames {
+    Video,
+    Subtitle,
+    Audio,
+    Font,
+    Image,
+    Archive,
+    Chapter,
 }
 
-fn extract_file_extension(file_name: &str) -> FileExtensionNames {
-    todo!()
+/// Find the kind of file in the registry, None for unknown extensions
+fn extract_file_extension(file_name: &str, file_extensions: &FileExtensions) -> Option<FileExtensionNames> {
+    // Test covered
+    let extension = string_get_file_extension(file_name)?;
+    file_extensions.0.iter()
+        .find(|(_, extensions)| extensions.iter().any(|i| i.eq_ignore_ascii_case(&extension)))
+        .map(|(kind, _)| *kind)
 }
 
 // String helper
 
 /// Removes all special characters in a string
+#[allow(dead_code)]
 fn string_remove_special_characters(input: &str) -> Result<String, ()> {
-    Ok(Regex::new(r#"[!@#$%^&*()_+{}\[\]:;"'<>,.?\|`~=-\\]"#).unwrap().replace_all(&input, " ").to_string())
+    Ok(Regex::new(r#"[!@#$%^&*()_+{}\[\]:;"'<>,.?\|`~=-\\]"#).unwrap().replace_all(input, " ").to_string())
 }
 
-/// Removes year numbers from string ranging from 1928 to 2030
+const YEAR_PATTERN: &str = r"([0-9]{4})(?:\.(?:0?[1-9]|1[0-2])(?:[^0-9]|$))?";
+const DUPLICATE_SPACES_PATTERN: &str = r"\s+";
+const ROMAN_NUMBER_PATTERN: &str = r"(?i)\s+M{0,4}(CM|CD|D?C{0,3})(XC|XL|L?X{0,3})(IX|IV|V?I{0,3})$";
+const EPISODE_RANGE_PATTERN: &str = r"[0-9]{1,3}-[0-9]{1,3}";
+const EMPTY_BRACKETS_PATTERN: &str = r"[\[\({})]\s*?[\]\)}]";
+
+/// Removes year numbers from string ranging from 1928 to 2030, together with a month like 2023.10
 fn string_remove_years(input: &str) -> Result<String, ()> {
     // Test covered
-    let reg = Regex::new(r"\d{4}").unwrap();
-    let candidates = reg.find(&input);
-    let mut result = input.to_string();
-    for year_candidate in candidates  {
-        let year = year_candidate.as_str().parse::<i32>().unwrap();
-        if year >= 1928 && year <= 2030 {
-            let reg = Regex::new(&year.to_string()).unwrap();
-            result = reg.replace(&result, " ").to_string();
-        }
-    };
+    let mut result = string_replace_years(input);
 
     // Remove duplicated spaces
     result = string_remove_duplicate_spaces(&result).unwrap();
@@ -344,34 +2072,150 @@ fn string_remove_years(input: &str) -> Result<String, ()> {
     Ok(result.trim().to_string())
 }
 
+static YEAR_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(YEAR_PATTERN).unwrap());
+
+/// Replaces years by a space, keeping what follows a month
+fn string_replace_years(input: &str) -> String {
+    YEAR_REGEX.replace_all(input, |caps: &regex::Captures| {
+        match caps[1].parse::<i32>() {
+            Ok(year) if (1928..=2030).contains(&year) => format!(" {}", caps[0].trim_start_matches(|c: char| c.is_ascii_digit() || c == '.')),
+            _ => caps[0].to_string(),
+        }
+    }).to_string()
+}
+
+static DUPLICATE_SPACES_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(DUPLICATE_SPACES_PATTERN).unwrap());
+
 /// Removes duplicate spaces in the string
 fn string_remove_duplicate_spaces(input: &str) -> Result<String, ()> {
     // Test covered
-    Ok(Regex::new(r"\s+").unwrap().replace_all(&input, " ").trim().to_string())
+    Ok(DUPLICATE_SPACES_REGEX.replace_all(input, " ").trim().to_string())
 }
 
+static ROMAN_NUMBER_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(ROMAN_NUMBER_PATTERN).unwrap());
+
 /// Remove roman numerals ranging from 1 to 13 from the string
 fn string_remove_roman_number(input: &str) -> Result<String, ()> {
-    Ok(Regex::new(r"(?i)\s+M{0,4}(CM|CD|D?C{0,3})(XC|XL|L?X{0,3})(IX|IV|V?I{0,3})$").unwrap().replace_all(&input, " ").to_string())
+    Ok(ROMAN_NUMBER_REGEX.replace_all(input, " ").to_string())
+}
+
+static SEASON_NUMBER_REGEXES: LazyLock<Vec<Regex>> = LazyLock::new(|| season_number_patterns().iter().map(|i| Regex::new(i).unwrap()).collect());
+
+/// Remove season markers like S2, Season 3, 2nd Season, Part 2, 第二季, a trailing Zoku or a batch like [S1+S2+OVA] from the string
+fn string_remove_season_number(input: &str) -> Result<String, ()> {
+    let mut result = input.to_string();
+    for reg in SEASON_NUMBER_REGEXES.iter() {
+        result = reg.replace_all(&result, " ").trim().to_string();
+    }
+    Ok(result)
+}
+
+/// Regexes of string_remove_season_number, in the order they run
+fn season_number_patterns() -> [String; 7] {
+    let sequel_markers: Vec<&str> = SEQUEL_MARKERS.iter().map(|(marker, _)| *marker).collect();
+    let sequel_prefixes: Vec<&str> = SEQUEL_PREFIXES.iter().map(|(marker, _)| *marker).collect();
+    [
+        r"(?i)\s*[\[(]\s*(?:S\d{1,2}|OVA|OAD|SPs?|Movie)(?:\s*[+&,~]\s*(?:S\d{1,2}|OVA|OAD|SPs?|Movie))+\s*[\])]".to_string(),
+        r"(?i)\s+(?:OVA|OAD|TVSP|SPs?|Specials?)s?$".to_string(),
+        r"(?i)\s*\b(?:part|cour)\s*(?:\d{1,2}|I{1,3}|IV|VI{0,3})\b".to_string(),
+        format!(r"(?i)\s*(?:\b\d{{1,2}}(?:st|nd|rd|th)|\b(?:{})|\bthe\s+final|\bfinal)\s+season\b", ORDINAL_WORDS.join("|")),
+        r"(?i)\s+(?:season\s*\d*|S\s*\d+)$".to_string(),
+        r"\s*(?:第\s*[\d一二三四五六七八九十]+\s*[季期部]|[一二三四五六七八九十]+期)".to_string(),
+        format!(r"(?i)^(?:{})\s+|\s+(?:{})$", sequel_prefixes.join("|"), sequel_markers.join("|")),
+    ]
+}
+
+/// Removes everything around the episode number which looks like a number, keeping version and end markers.
+/// Bare years stay, they may be episode numbers of long running series
+fn string_clean_episode_name(file_name: &str, filter_words: &FilterWords) -> String {
+    let mut middleware = string_remove_file_extension(file_name);
+    middleware = string_remove_crc(&middleware);
+    middleware = string_remove_season_marker(&middleware);
+    middleware = string_remove_filtered(&middleware, filter_words).unwrap();
+    middleware = string_remove_dated_years(&middleware);
+    middleware = string_remove_empty_brackets(&middleware).unwrap();
+    middleware = string_remove_duplicate_spaces(&middleware).unwrap();
+    middleware.trim().to_string()
+}
+
+/// Vertical resolutions, which are rarely episode numbers
+const RESOLUTION_NUMBERS: [u16; 8] = [360, 480, 540, 576, 720, 1080, 1440, 2160];
+
+static DATED_YEAR_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[\[(]\s*(?:19|20)\d{2}\s*[\])]|(?:19|20)\d{2}\.(?:0?[1-9]|1[0-2])(?:[^\d]|$)").unwrap());
+
+/// Removes years which can not be episode numbers, like (2019), [2019] or 2023.10
+fn string_remove_dated_years(input: &str) -> String {
+    DATED_YEAR_REGEX.replace_all(input, " ").to_string()
+}
+
+static DECIMAL_EPISODE_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?:^|[\s\[(\-_#第])(\d{1,4}\.[1-9])(?:[\s\])\-_vV話话集]|$)").unwrap());
+
+/// Finds decimal episode number like 12.5 of a recap episode in a name cleaned by string_clean_episode_name
+fn string_find_decimal_episode(clean_name: &str) -> Option<String> {
+    // Test covered
+    DECIMAL_EPISODE_REGEX.captures(clean_name).map(|caps| caps[1].to_string())
+}
+
+static EPISODE_VERSION_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\d[vV](\d{1,2})(?:[^[:alnum:]]|END|$)").unwrap());
+
+/// Finds release version like 2 in 07v2 in a cleaned name, 1 when not revised
+fn string_find_episode_version(clean_name: &str) -> u8 {
+    // Test covered
+    EPISODE_VERSION_REGEX.captures(clean_name)
+        .and_then(|caps| caps[1].parse::<u8>().ok())
+        .unwrap_or(1)
 }
 
-fn string_find_episode_number(file_name: &str) -> Result<Vec<u16>, ()> {
+static FINALE_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\d(?:[vV]\d{1,2})?\s*(?:END|Fin|FIN|完)(?:[^[:alpha:]]|$)").unwrap());
+
+/// Checks whether episode number in a cleaned name carries an end marker, like 12END, 12 END or 13 Fin
+fn string_is_finale(clean_name: &str) -> bool {
+    // Test covered
+    FINALE_REGEX.is_match(clean_name)
+}
+
+static MULTI_EPISODE_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)(?:^|[\s\[(_#]|E)(\d{1,4})(?:(?:-|~|\s*\+\s*|\s*&\s*)E?|E)(\d{1,4})(?:$|[\s\])_])").unwrap());
+
+/// Finds first and last episode of a multi-episode file in a cleaned name, like 01-02, 01+02 or S01E01E02
+fn string_find_episode_range(clean_name: &str) -> Option<(u16, u16)> {
+    // Test covered
+    let clean_name = string_remove_episode_version(clean_name);
+    let caps = MULTI_EPISODE_REGEX.captures(&clean_name)?;
+    let first = caps[1].parse::<u16>().ok()?;
+    let last = caps[2].parse::<u16>().ok()?;
+
+    // A whole batch like 01-13 is no multi-episode file
+    match last > first && last - first <= 3 {
+        true => Some((first, last)),
+        false => None,
+    }
+}
+
+static EPISODE_VERSION_SUFFIX_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(\d)[vV]\d{1,2}([^[:alnum:]]|END|$)").unwrap());
+
+/// Removes release version behind episode number, like v2 in 07v2
+fn string_remove_episode_version(input: &str) -> String {
+    EPISODE_VERSION_SUFFIX_REGEX.replace_all(input, "$1$2").to_string()
+}
+
+static TRAILING_ROMAN_NUMERAL_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)\s+(M{0,4}(?:CM|CD|D?C{0,3})(?:XC|XL|L?X{0,3})(?:IX|IV|V?I{0,3}))$").unwrap());
+static DIGITS_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\d+").unwrap());
+
+/// Finds episode number candidates in a name cleaned by string_clean_episode_name
+fn string_find_episode_number(clean_name: &str) -> Result<Vec<u16>, ()> {
     let mut result = Vec::<u16>::new();
 
-    let clean_name = {
-        let mut middleware = string_remove_file_extension(&file_name);
-        middleware = string_remove_filtered(&middleware).unwrap();
-        middleware = string_remove_years(&middleware).unwrap();
-        middleware = string_remove_empty_brackets(&middleware).unwrap();
-        middleware = string_remove_duplicate_spaces(&middleware).unwrap();
-        middleware.trim().to_string()
-    };
+    let range = string_find_episode_range(clean_name);
+    let clean_name = string_remove_episode_version(clean_name);
     // Clean name should contain:
     // Episode number and episode name (may contain numbers).
 
     // Deal with roman numerals first
-    for (_, [roman_numeral]) in Regex::new(r"(?i)\s+M{0,4}(CM|CD|D?C{0,3})(XC|XL|L?X{0,3})(IX|IV|V?I{0,3})$").unwrap().captures_iter(&clean_name).map(|c| c.extract()) {
-        match roman_to_int(&roman_numeral) {
+    for (_, [roman_numeral]) in TRAILING_ROMAN_NUMERAL_REGEX.captures_iter(&clean_name).map(|c| c.extract()) {
+        if roman_numeral.is_empty() {
+            continue;
+        }
+        match roman_to_int(roman_numeral) {
             Ok(episode_number_guess) => {
                 debug!("Find episode number candidates {}", episode_number_guess);
                 result.push(episode_number_guess);
@@ -382,11 +2226,20 @@ fn string_find_episode_number(file_name: &str) -> Result<Vec<u16>, ()> {
         }
     }
 
-    // Deal with common numbers
-    for (_ ,[common_number]) in Regex::new(r#"[[[:alpha:]]\s\[\({\-_](\d{1,2})[[[:alpha:]]\s\]\)}\-_]*"#).unwrap().captures_iter(&clean_name).map(|c| c.extract()) {
-        debug!("{}", &common_number);
-        // result.push(common_number);
-        match common_number.parse::<u16>() {
+    // Deal with common numbers, up to 4 digits for long running series
+    // Numbers looking like years or resolutions go last, only context can tell them from episodes like One Piece 1080
+    let mut unlikely = Vec::<u16>::new();
+    for common_number in DIGITS_REGEX.find_iter(&clean_name) {
+        let delimited = clean_name[..common_number.start()].chars().last()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c.is_whitespace() || "[({-_#第".contains(c));
+        if !delimited || common_number.as_str().len() > 4 {
+            continue;
+        }
+        match common_number.as_str().parse::<u16>() {
+            Ok(success) if (1928..=2030).contains(&success) || RESOLUTION_NUMBERS.contains(&success) => {
+                debug!("Find unlikely episode number candidate {}", success);
+                unlikely.push(success)
+            },
             Ok(success) => {
                 debug!("Find episode number candidate {}", success);
                 result.push(success)
@@ -396,61 +2249,59 @@ fn string_find_episode_number(file_name: &str) -> Result<Vec<u16>, ()> {
             },
         }
     }
+    result.append(&mut unlikely);
+
+    // Later episodes of a multi-episode file are covered by the first one
+    if let Some((first, last)) = range {
+        result.retain(|candidate| *candidate <= first || *candidate > last);
+    }
+
+    // A number written twice, like season 2 episode 2, is a single candidate
+    let mut found = HashSet::new();
+    result.retain(|candidate| found.insert(*candidate));
 
     Ok(result)
 }
 
+static REMOVED_SQUARE_BRACKETS_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[[^\]]*?(%ReM0vE%)[^\[]*?\]").unwrap());
+static REMOVED_ROUND_BRACKETS_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\([^\]]*?(%ReM0vE%)[^\[]*?\)").unwrap());
+
 /// Remove CC names and Meta tags in given string
-fn string_remove_filtered(input: &str) -> Result<String, ()> {
+fn string_remove_filtered(input: &str, filter_words: &FilterWords) -> Result<String, ()> {
+    let mut result = input.to_string();
+
     // Remove CC names
-    let filter_words = FilterWords::load();
-    let mut result = {
-        let filter_construct_middleware: Vec<String> = filter_words.cc_group.iter()
-            .map(|i| format!("({})", i))
-            .collect();
-    
-        let combined = filter_construct_middleware.join("|");
-        let reg_str = format!(r"(?i){}(&{})*?", combined, combined);
-        let reg = Regex::new(&reg_str).expect("Invalid regex pattern");
-        reg.replace_all(&input, "%ReM0vE%").to_string()
-    };
+    if let Some(reg) = &filter_words.cc_group_regex {
+        result = reg.replace_all(&result, "%ReM0vE%").to_string();
+    }
 
-    // Remove Meta Tags
-    result = {
-        let filter_construct_middleware: Vec<String> = filter_words.meta_tag.iter()
-            .map(|i| format!("({})", i))
-            .collect();
-    
-        let combined = filter_construct_middleware.join("|");
-        let reg_str = format!(r"(?i){}(&{})*?", combined, combined);
-        let reg = Regex::new(&reg_str).expect("Invalid regex pattern");
-        reg.replace_all(&result, "%ReM0vE%").to_string()
-    };
+    // Remove Meta Tags
+    if let Some(reg) = &filter_words.meta_tag_regex {
+        result = reg.replace_all(&result, "%ReM0vE%").to_string();
+    }
 
     // Remove REMOVE
-    result = {
-        let reg = Regex::new(r"\[[^\]]*?(%ReM0vE%)[^\[]*?\]").unwrap();
-        reg.replace_all(&result, " ").to_string()
-    };
-    result = {
-        let reg = Regex::new(r"\([^\]]*?(%ReM0vE%)[^\[]*?\)").unwrap();
-        reg.replace_all(&result, " ").to_string()
-    };
+    result = REMOVED_SQUARE_BRACKETS_REGEX.replace_all(&result, " ").to_string();
+    result = REMOVED_ROUND_BRACKETS_REGEX.replace_all(&result, " ").to_string();
 
     debug!("After removing filtered words: {}", &result);
 
     Ok(result)
 }
 
+static SYMBOL_BRACKETS_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[\W*?\]").unwrap());
+
 /// Remove square brackets with content inside (Brutal)
 fn string_remove_square_brackets(input: &str) -> Result<String, ()> {
      // TODO: Not using this
-    Ok(Regex::new(r"\[\W*?\]").unwrap().replace_all(&input, " ").to_string())
+    Ok(SYMBOL_BRACKETS_REGEX.replace_all(input, " ").to_string())
 }
 
+static EPISODE_RANGE_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(EPISODE_RANGE_PATTERN).unwrap());
+
 /// Remove things like [01-13]
 fn string_remove_episode_range(input: &str) -> Result<String, ()> {
-    let mut result = Regex::new(r"\d{1,3}-\d{1,3}").unwrap().replace_all(&input, " ").to_string();
+    let mut result = EPISODE_RANGE_REGEX.replace_all(input, " ").to_string();
 
     // Remove empty brackets
     result = string_remove_empty_brackets(&result).unwrap();
@@ -458,31 +2309,101 @@ fn string_remove_episode_range(input: &str) -> Result<String, ()> {
     Ok(result)
 }
 
+static EMPTY_BRACKETS_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(EMPTY_BRACKETS_PATTERN).unwrap());
+
 /// Remove empty brackets like [ ] ( ) {  }
 /// Naive algorithm is used
 fn string_remove_empty_brackets(input: &str) -> Result<String, ()> {
     // TODO: Change algorithm to allow nested empty brackets
-    Ok(Regex::new(r"[\[\({})]\s*?[\]\)}]").unwrap().replace_all(&input, " ").to_string())
+    Ok(EMPTY_BRACKETS_REGEX.replace_all(input, " ").to_string())
 }
 
 /// Removes english characters from string
+#[allow(dead_code)]
 fn string_remove_english_characters(input: &str) -> Result<String, ()> {
-    Ok(Regex::new(r"[A-Za-z]").unwrap().replace_all(&input, " ").trim().to_string())
+    Ok(Regex::new(r"[A-Za-z]").unwrap().replace_all(input, " ").trim().to_string())
+}
+
+static FILE_EXTENSION_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\.(\w{2,4})$").unwrap());
+
+/// Gets lower case file extension name from string
+fn string_get_file_extension(input: &str) -> Option<String> {
+    FILE_EXTENSION_REGEX.captures(input).map(|caps| caps[1].to_lowercase())
+}
+
+static PATH_CHARACTERS_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"[/\\:*?"<>|]"#).unwrap());
+
+/// Replaces characters not allowed in path components
+fn string_sanitize_path(input: &str) -> String {
+    string_remove_duplicate_spaces(&PATH_CHARACTERS_REGEX.replace_all(input, " ")).unwrap()
+}
+
+/// Regex of a word without letters directly around it, ignoring case
+fn word_pattern(word: &str) -> String {
+    format!(r"(?i)(?:^|[^[:alpha:]]){}(?:[^[:alpha:]]|$)", regex::escape(word))
+}
+
+/// Check whether word appears in string without letters directly around it, ignoring case
+fn string_contains_word(input: &str, word: &str) -> bool {
+    Regex::new(&word_pattern(word)).unwrap().is_match(input)
+}
+
+/// Gets the last component of a path
+fn string_get_file_name(input: &str) -> String {
+    match Path::new(input).file_name() {
+        Some(file_name) => file_name.to_string_lossy().to_string(),
+        None => input.to_string(),
+    }
+}
+
+static BRACKET_CONTENT_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[\[(]([^\])]*)[\])]").unwrap());
+static BRACKETS_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[\[(][^\])]*[\])]").unwrap());
+
+/// Keeps only the parts of a file name where language tags live, bracket contents and dotted suffixes
+fn string_get_language_tags(input: &str) -> String {
+    let file_name = string_remove_file_extension(&string_get_file_name(input));
+    let mut result: Vec<String> = BRACKET_CONTENT_REGEX.captures_iter(&file_name)
+        .map(|caps| caps[1].to_string())
+        .collect();
+    let without_brackets = BRACKETS_REGEX.replace_all(&file_name, " ").to_string();
+    result.extend(without_brackets.split('.').skip(1).map(|i| i.to_string()));
+    result.join(" ")
+}
+
+static CRC_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[([0-9A-Fa-f]{8})\]").unwrap());
+
+/// Finds CRC32 checksum tag like [1A2B3C4D] in string
+fn string_find_crc(input: &str) -> Option<String> {
+    CRC_REGEX.captures(input).map(|caps| caps[1].to_uppercase())
+}
+
+static SEASON_MARKER_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)(^|[^[:alnum:]])S\d{1,2}(E\d|\s*-\s*\d)").unwrap());
+static STANDALONE_SEASON_MARKER_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)(^|[^[:alnum:]])S\d{1,2}([^[:alnum:]]|$)").unwrap());
+
+/// Removes season marker in front of episode number, like S01 in S01E05 or S2 in "S2 - 05", and standalone ones like S2 in "S2 [02]"
+fn string_remove_season_marker(input: &str) -> String {
+    let result = SEASON_MARKER_REGEX.replace_all(input, "$1 $2");
+    STANDALONE_SEASON_MARKER_REGEX.replace_all(&result, "$1 $2").to_string()
+}
+
+/// Removes CRC32 checksum tags like [1A2B3C4D] from string
+fn string_remove_crc(input: &str) -> String {
+    CRC_REGEX.replace_all(input, " ").to_string()
 }
 
 /// Removes file extension name from string
 fn string_remove_file_extension(input: &str) -> String {
-    Regex::new(r"\.\w{2,4}$").unwrap().replace_all(&input, " ").trim().to_string()
+    FILE_EXTENSION_REGEX.replace_all(input, " ").trim().to_string()
 }
 
 #[cfg(test)]
 mod tests {
     use core::panic;
-    use std::{fs, io::Read};
+    use std::fs;
 
-    use anime_organizer_rs::load_env_var;
-    use log::{warn, error, info};
-    use crate::series::{Series, FilterWords};
+    use crate::load_env_var;
+    use log::{error, info};
+    use crate::series::FilterWords;
 
     fn setup() {
         // Load env
@@ -494,7 +2415,7 @@ mod tests {
     }
 
     fn load_test_sheet(test_sheet_name: &str) -> String {
-        let tests_series_names_path = load_env_var(&test_sheet_name).unwrap();
+        let tests_series_names_path = load_env_var(test_sheet_name).unwrap();
         info!("Series Name test sheet: {}", &tests_series_names_path);
 
         match fs::read_to_string(tests_series_names_path) {
@@ -523,7 +2444,7 @@ mod tests {
             series_name: String,
         }
 
-        let test_sheet: Vec<SeriesName> = serde_json::from_str(&load_test_sheet(&"TEST_SERIES_NAME".to_string())).expect("JSON was not well-formatted");
+        let test_sheet: Vec<SeriesName> = serde_json::from_str(&load_test_sheet("TEST_SERIES_NAME")).expect("JSON was not well-formatted");
         
         // Filter words
         let filter_words = FilterWords::load();
@@ -547,9 +2468,11 @@ mod tests {
         struct SeasonNumber {
             folder_name: String,
             season_number: i16,
+            #[serde(default)]
+            split_cour: super::SplitCour,
         }
 
-        let test_sheet: Vec<SeasonNumber> = serde_json::from_str(&load_test_sheet(&"TEST_SERIES_SEASON_NUMBER".to_string())).expect("JSON was not well-formatted");
+        let test_sheet: Vec<SeasonNumber> = serde_json::from_str(&load_test_sheet("TEST_SERIES_SEASON_NUMBER")).expect("JSON was not well-formatted");
 
         // Filter words
         let filter_words = FilterWords::load();
@@ -557,7 +2480,92 @@ mod tests {
         // Run test
         for i in test_sheet.iter() {
             info!("{}: {}", &i.folder_name, &i.season_number);
-            assert_eq!(super::extract_series_season_number(&i.folder_name, &filter_words).unwrap().to_string(), i.season_number.to_string());
+            assert_eq!(super::extract_series_season_number(&i.folder_name, &filter_words, i.split_cour).unwrap().to_string(), i.season_number.to_string());
+        };
+
+        // Final season does not tell its number
+        assert!(super::extract_series_season_number("[VCB-Studio] Shingeki no Kyojin The Final Season [Ma10p_1080p]", &filter_words, super::SplitCour::default()).is_err());
+    }
+
+    #[test]
+    fn file_season_number_extraction() {
+        // Setup
+        setup();
+
+        // Load test sheet
+        use serde::Deserialize;
+        use super::SplitCour;
+
+        #[derive(Deserialize)]
+        struct FileSeasonNumber {
+            file_name: String,
+            season_number: Option<u16>,
+        }
+
+        let test_sheet: Vec<FileSeasonNumber> = serde_json::from_str(&load_test_sheet("TEST_FILE_SEASON_NUMBER")).expect("JSON was not well-formatted");
+
+        // Filter words
+        let filter_words = FilterWords::load();
+
+        // Run test
+        for i in test_sheet.iter() {
+            info!("{}: {:?}", &i.file_name, &i.season_number);
+            assert_eq!(super::extract_file_season_number(&i.file_name, &filter_words, SplitCour::default()), i.season_number);
+        };
+    }
+
+    #[test]
+    fn episode_numbering_conversion() {
+        // Setup
+        setup();
+
+        // Load test sheet
+        use serde::Deserialize;
+
+        #[derive(Deserialize)]
+        struct EpisodeNumbering {
+            episode_counts: Vec<u16>,
+            absolute: u16,
+            season: u16,
+            episode: u16,
+        }
+
+        let test_sheet: Vec<EpisodeNumbering> = serde_json::from_str(&load_test_sheet("TEST_EPISODE_NUMBERING_CONVERSION")).expect("JSON was not well-formatted");
+
+        // Run test
+        use super::{absolute_to_seasonal, seasonal_to_absolute};
+        for i in test_sheet.iter() {
+            info!("{:?} {}: S{:02}E{:02}", &i.episode_counts, &i.absolute, &i.season, &i.episode);
+            assert_eq!(absolute_to_seasonal(i.absolute, &i.episode_counts), (i.season, i.episode));
+            assert_eq!(seasonal_to_absolute(i.season, i.episode, &i.episode_counts), Some(i.absolute));
+        }
+
+        assert_eq!(seasonal_to_absolute(3, 1, &[12]), None);
+    }
+
+    #[test]
+    fn series_year_extraction() {
+        // Setup
+        setup();
+
+        // Load test sheet
+        use serde::Deserialize;
+
+        #[derive(Deserialize)]
+        struct SeriesYear {
+            folder_name: String,
+            year: Option<u16>,
+        }
+
+        let test_sheet: Vec<SeriesYear> = serde_json::from_str(&load_test_sheet("TEST_SERIES_YEAR")).expect("JSON was not well-formatted");
+
+        // Filter words
+        let filter_words = FilterWords::load();
+
+        // Run test
+        for i in test_sheet.iter() {
+            info!("{}: {:?}", &i.folder_name, &i.year);
+            assert_eq!(super::extract_series_year(&i.folder_name, &filter_words), i.year);
         };
     }
 
@@ -575,7 +2583,7 @@ mod tests {
             result: String,
         }
 
-        let test_sheet: Vec<SpacedString> = serde_json::from_str(&load_test_sheet(&"TEST_STRING_SPACE_DEDUPLICATION".to_string())).expect("JSON was not well-formatted");
+        let test_sheet: Vec<SpacedString> = serde_json::from_str(&load_test_sheet("TEST_STRING_SPACE_DEDUPLICATION")).expect("JSON was not well-formatted");
 
         // Run test
         use super::string_remove_duplicate_spaces;
@@ -599,7 +2607,7 @@ mod tests {
             result: String,
         }
 
-        let test_sheet: Vec<SpacedString> = serde_json::from_str(&load_test_sheet(&"TEST_STRING_YEAR_REMOVAL".to_string())).expect("JSON was not well-formatted");
+        let test_sheet: Vec<SpacedString> = serde_json::from_str(&load_test_sheet("TEST_STRING_YEAR_REMOVAL")).expect("JSON was not well-formatted");
 
         // Run test
         use super::string_remove_years;
@@ -623,13 +2631,75 @@ mod tests {
             episode_number: u16,
         }
 
-        let test_sheet: Vec<EpisodeNumber> = serde_json::from_str(&load_test_sheet(&"TEST_STRING_EPISODE_NUMBER_DISCOVERY".to_string())).expect("JSON was not well-formatted");
+        let test_sheet: Vec<EpisodeNumber> = serde_json::from_str(&load_test_sheet("TEST_STRING_EPISODE_NUMBER_DISCOVERY")).expect("JSON was not well-formatted");
+
+        // Filter words
+        let filter_words = FilterWords::load();
 
         // Run test
-        use super::string_find_episode_number;
+        use super::{string_clean_episode_name, string_find_episode_number};
         for i in test_sheet.iter() {
             info!("{}: {}", &i.file_name, &i.episode_number);
-            assert_eq!(string_find_episode_number(&i.file_name).unwrap()[0], i.episode_number);
+            assert_eq!(string_find_episode_number(&string_clean_episode_name(&i.file_name, &filter_words)).unwrap()[0], i.episode_number);
+        }
+    }
+
+    #[test]
+    fn episode_variant_detection() {
+        // Setup
+        setup();
+
+        // Load test sheet
+        use serde::Deserialize;
+
+        #[derive(Deserialize)]
+        struct EpisodeVariant {
+            file_name: String,
+            decimal: Option<String>,
+            version: u8,
+            finale: bool,
+        }
+
+        let test_sheet: Vec<EpisodeVariant> = serde_json::from_str(&load_test_sheet("TEST_EPISODE_VARIANT_DETECTION")).expect("JSON was not well-formatted");
+
+        // Filter words
+        let filter_words = FilterWords::load();
+
+        // Run test
+        use super::{string_clean_episode_name, string_find_decimal_episode, string_find_episode_version, string_is_finale};
+        for i in test_sheet.iter() {
+            info!("{}: {:?} v{} {}", &i.file_name, &i.decimal, &i.version, &i.finale);
+            let clean_name = string_clean_episode_name(&i.file_name, &filter_words);
+            assert_eq!(string_find_decimal_episode(&clean_name), i.decimal);
+            assert_eq!(string_find_episode_version(&clean_name), i.version);
+            assert_eq!(string_is_finale(&clean_name), i.finale);
+        }
+    }
+
+    #[test]
+    fn episode_range_detection() {
+        // Setup
+        setup();
+
+        // Load test sheet
+        use serde::Deserialize;
+
+        #[derive(Deserialize)]
+        struct EpisodeRange {
+            file_name: String,
+            range: Option<(u16, u16)>,
+        }
+
+        let test_sheet: Vec<EpisodeRange> = serde_json::from_str(&load_test_sheet("TEST_EPISODE_RANGE_DETECTION")).expect("JSON was not well-formatted");
+
+        // Filter words
+        let filter_words = FilterWords::load();
+
+        // Run test
+        use super::{string_clean_episode_name, string_find_episode_range};
+        for i in test_sheet.iter() {
+            info!("{}: {:?}", &i.file_name, &i.range);
+            assert_eq!(string_find_episode_range(&string_clean_episode_name(&i.file_name, &filter_words)), i.range);
         }
     }
 
@@ -644,23 +2714,305 @@ mod tests {
         #[derive(Serialize, Deserialize)]
         struct EpisodeNumber {
             file_names: Vec<String>,
-            episode_numbers: Vec<u16>,
+            /// null for files left unresolved
+            episode_numbers: Vec<Option<u16>>,
+            #[serde(default)]
+            rules: Vec<Option<super::EpisodeNumberRule>>,
         }
 
-        let test_sheet: Vec<EpisodeNumber> = serde_json::from_str(&load_test_sheet(&"TEST_EPISODE_NUMBER_EXTRACTION_WITH_CONTEXT".to_string())).expect("JSON was not well-formatted");
+        let test_sheet: Vec<EpisodeNumber> = serde_json::from_str(&load_test_sheet("TEST_EPISODE_NUMBER_EXTRACTION_WITH_CONTEXT")).expect("JSON was not well-formatted");
+
+        // Filter words
+        let filter_words = FilterWords::load();
 
         // Run test
         use super::extract_episode_number;
         for i in test_sheet {
             // Find episode number
-            let result = extract_episode_number(i.file_names).unwrap();
+            let result = extract_episode_number(i.file_names, &filter_words);
 
-            // Assert result
+            // Assert result, one per file in the same order
+            assert_eq!(result.len(), i.episode_numbers.len());
             let assert_iter = result.iter().zip(i.episode_numbers.iter()); // Zip two chile elements of episode number together
-            for (prediction, ground_truth) in assert_iter {
-                info!("{}: {}", &prediction.sequence, &ground_truth);
-                assert_eq!(prediction.sequence, *ground_truth);
+            for (index, (prediction, ground_truth)) in assert_iter.enumerate() {
+                let (sequence, rule) = match prediction {
+                    super::EpisodeNumber::Resolved(episode) => (Some(episode.sequence), episode.rule),
+                    super::EpisodeNumber::Unresolved(_) => (None, None),
+                };
+                info!("{:?}: {:?}", &sequence, &ground_truth);
+                assert_eq!(sequence, *ground_truth);
+                if let Some(expected_rule) = i.rules.get(index) {
+                    assert_eq!(rule, *expected_rule);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn season_consistency_check() {
+        // Setup
+        setup();
+
+        // Load test sheet
+        use serde::Deserialize;
+        use super::{FileExtensions, Season, SeasonIssueKind, SeriesOverride};
+
+        #[derive(Deserialize)]
+        struct SeasonConsistency {
+            file_names: Vec<String>,
+            missing: Vec<u16>,
+            duplicate: Vec<u16>,
+            outlier: Vec<u16>,
+        }
+
+        let test_sheet: Vec<SeasonConsistency> = serde_json::from_str(&load_test_sheet("TEST_SEASON_CONSISTENCY")).expect("JSON was not well-formatted");
+
+        // Filter words and file extensions
+        let filter_words = FilterWords::load();
+        let file_extensions = FileExtensions::load();
+
+        // Run test
+        for i in test_sheet {
+            let season = Season::new("Show", 1, i.file_names, &filter_words, &file_extensions, &SeriesOverride::default()).unwrap();
+            let issues = season.check("Show");
+            for (kind, expected) in [(SeasonIssueKind::Missing, i.missing), (SeasonIssueKind::Duplicate, i.duplicate), (SeasonIssueKind::Outlier, i.outlier)] {
+                let episodes: Vec<u16> = issues.iter()
+                    .filter(|issue| issue.kind == kind)
+                    .flat_map(|issue| issue.episodes.clone())
+                    .collect();
+                info!("{:?}: {:?}", &kind, &episodes);
+                assert_eq!(episodes, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn name_explanation() {
+        // Setup
+        setup();
+
+        // Load test sheet
+        use serde::Deserialize;
+        use super::{explain_name, FileExtensions, SplitCour};
+
+        #[derive(Deserialize)]
+        struct NameStage {
+            stage: String,
+            result: String,
+            #[serde(default)]
+            fired: Vec<String>,
+        }
+
+        #[derive(Deserialize)]
+        struct NameExplanation {
+            name: String,
+            series_name: Option<String>,
+            season: u16,
+            episode_numbers: Vec<u16>,
+            #[serde(default)]
+            series_name_stages: Vec<NameStage>,
+        }
+
+        let test_sheet: Vec<NameExplanation> = serde_json::from_str(&load_test_sheet("TEST_NAME_EXPLANATION")).expect("JSON was not well-formatted");
+
+        // Filter words and file extensions
+        let filter_words = FilterWords::load();
+        let file_extensions = FileExtensions::load();
+
+        // Run test
+        for i in test_sheet {
+            let explanation = explain_name(&i.name, &filter_words, &file_extensions, SplitCour::default());
+            info!("{}: {:?}", &i.name, &explanation);
+            assert_eq!(explanation.series_name, i.series_name);
+            assert_eq!(explanation.season, i.season);
+            assert_eq!(explanation.episode_numbers, i.episode_numbers);
+            if i.series_name_stages.is_empty() {
+                continue;
+            }
+            assert_eq!(explanation.series_name_stages.len(), i.series_name_stages.len());
+            for (stage, expected) in explanation.series_name_stages.iter().zip(i.series_name_stages) {
+                assert_eq!(stage.stage, expected.stage);
+                assert_eq!(stage.result, expected.result);
+                assert_eq!(stage.fired.iter().map(|fired| fired.text.clone()).collect::<Vec<String>>(), expected.fired);
+            }
+        }
+    }
+
+    #[test]
+    fn episode_type_classification() {
+        // Setup
+        setup();
+
+        // Load test sheet
+        use serde::Deserialize;
+        use super::EpisodeType;
+
+        #[derive(Deserialize)]
+        struct EpisodeTypeClassification {
+            file_name: String,
+            episode_type: EpisodeType,
+        }
+
+        let test_sheet: Vec<EpisodeTypeClassification> = serde_json::from_str(&load_test_sheet("TEST_EPISODE_TYPE_CLASSIFICATION")).expect("JSON was not well-formatted");
+
+        // Filter words
+        let filter_words = FilterWords::load();
+
+        // Run test
+        use super::extract_episode_type;
+        for i in test_sheet.iter() {
+            info!("{}: {:?}", &i.file_name, &i.episode_type);
+            assert_eq!(extract_episode_type(&i.file_name, &filter_words), i.episode_type);
+        }
+    }
+
+    #[test]
+    fn file_extension_kind() {
+        // Setup
+        setup();
+
+        // Load test sheet
+        use serde::Deserialize;
+        use super::{FileExtensionNames, FileExtensions};
+
+        #[derive(Deserialize)]
+        struct FileExtensionKind {
+            file_name: String,
+            kind: Option<FileExtensionNames>,
+        }
+
+        let test_sheet: Vec<FileExtensionKind> = serde_json::from_str(&load_test_sheet("TEST_FILE_EXTENSION_KIND")).expect("JSON was not well-formatted");
+
+        // File extensions
+        let file_extensions = FileExtensions::load();
+
+        // Run test
+        use super::extract_file_extension;
+        for i in test_sheet.iter() {
+            info!("{}: {:?}", &i.file_name, &i.kind);
+            assert_eq!(extract_file_extension(&i.file_name, &file_extensions), i.kind);
+        }
+    }
+
+    #[test]
+    fn subtitle_language_detection() {
+        // Setup
+        setup();
+
+        // Load test sheet
+        use serde::Deserialize;
+
+        #[derive(Deserialize)]
+        struct SubtitleLanguage {
+            raw: String,
+            language: Option<String>,
+        }
+
+        let test_sheet: Vec<SubtitleLanguage> = serde_json::from_str(&load_test_sheet("TEST_SUBTITLE_LANGUAGE_DETECTION")).expect("JSON was not well-formatted");
+
+        // Run test
+        use super::extract_subtitle_language;
+        for i in test_sheet.iter() {
+            info!("{}: {:?}", &i.raw, &i.language);
+            assert_eq!(extract_subtitle_language(&i.raw), i.language);
+        }
+    }
+
+    #[test]
+    fn release_info_extraction() {
+        // Setup
+        setup();
+
+        // Load test sheet
+        use serde::Deserialize;
+        use super::ReleaseInfo;
+
+        #[derive(Deserialize)]
+        struct ReleaseInfoExtraction {
+            name: String,
+            release: ReleaseInfo,
+        }
+
+        let test_sheet: Vec<ReleaseInfoExtraction> = serde_json::from_str(&load_test_sheet("TEST_RELEASE_INFO_EXTRACTION")).expect("JSON was not well-formatted");
+
+        // Filter words
+        let filter_words = FilterWords::load();
+
+        // Run test
+        use super::extract_release_info;
+        for i in test_sheet.iter() {
+            info!("{}: {}", &i.name, &i.release);
+            assert_eq!(extract_release_info(&i.name, &filter_words), i.release);
+        }
+    }
+
+    #[test]
+    fn series_organization() {
+        // Setup
+        setup();
+
+        // Load test sheet
+        use serde::{Deserialize, Serialize};
+        use std::collections::HashMap;
+        use crate::naming::{Naming, NamingProfile, Numbering};
+        use super::{EpisodeCounts, FileExtensions, Overrides, SplitCour};
+
+        #[derive(Serialize, Deserialize)]
+        struct SeriesOrganization {
+            folder_name: String,
+            #[serde(default)]
+            naming_profile: NamingProfile,
+            default_subtitle_language: Option<String>,
+            naming_template: Option<String>,
+            #[serde(default)]
+            split_cour: SplitCour,
+            #[serde(default)]
+            numbering: Numbering,
+            #[serde(default)]
+            episode_counts: Vec<u16>,
+            file_names: Vec<String>,
+            target_paths: Vec<String>,
+        }
+
+        let test_sheet: Vec<SeriesOrganization> = serde_json::from_str(&load_test_sheet("TEST_SERIES_ORGANIZATION")).expect("JSON was not well-formatted");
+
+        // Filter words and file extensions
+        let filter_words = FilterWords::load();
+        let file_extensions = FileExtensions::load();
+
+        // Build source folders in temp directory
+        let test_directory = std::env::temp_dir().join(format!("anime-organizer-rs-series-organization-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&test_directory);
+
+        // Run test
+        use super::Series;
+        use crate::materialize::LinkMode;
+        for i in test_sheet {
+            let folder_path = test_directory.join(&i.folder_name);
+            fs::create_dir_all(&folder_path).unwrap();
+            for file_name in &i.file_names {
+                fs::create_dir_all(folder_path.join(file_name).parent().unwrap()).unwrap();
+                fs::write(folder_path.join(file_name), file_name).unwrap();
             }
+
+            let mut series = Series::new(&folder_path.to_string_lossy(), i.split_cour, &Overrides::default(), &[], &filter_words, &file_extensions).unwrap();
+            series.renumber(&EpisodeCounts(HashMap::from([(series.name().to_string(), i.episode_counts)])));
+            let target_directory = test_directory.join("target");
+            let naming = Naming {
+                profile: i.naming_profile,
+                default_subtitle_language: i.default_subtitle_language,
+                template: i.naming_template,
+                numbering: i.numbering,
+            };
+            let mut target_paths: Vec<String> = series.plan(&target_directory.to_string_lossy(), LinkMode::default(), &naming).iter()
+                .map(|entry| entry.target.strip_prefix(&target_directory).unwrap().to_string_lossy().to_string())
+                .collect();
+            target_paths.sort();
+
+            info!("{}: {:?}", &i.folder_name, &target_paths);
+            assert_eq!(target_paths, i.target_paths);
         }
+
+        let _ = fs::remove_dir_all(&test_directory);
     }
 }
\ No newline at end of file